@@ -15,8 +15,8 @@ use crate::{
     },
     extra::{bits, parse::algebraic_square_to_number},
     movegen::{
-        defs::{print_bitboard, Move, Shift},
-        PROMOTION_PIECES,
+        defs::{print_bitboard, Move, MoveList, MoveType, Shift},
+        MoveGenerator, PROMOTION_PIECES,
     },
 };
 use std::sync::Arc;
@@ -244,6 +244,25 @@ impl Board {
         move_data
     }
 
+    // All legal moves in the current position. make_move() takes back
+    // illegal moves by itself, legal ones are taken back here.
+    pub fn legal_moves(&self, movegen: &MoveGenerator) -> Vec<Move> {
+        let mut move_list = MoveList::new();
+        movegen.generate_moves(self, &mut move_list, MoveType::All);
+
+        let mut board = self.clone();
+        let mut legal = vec![];
+        for i in 0..move_list.len() {
+            let m = move_list.get_move(i);
+            if board.make_move(m, movegen) {
+                board.unmake();
+                legal.push(m);
+            }
+        }
+
+        legal
+    }
+
     // End helper functions
 }
 
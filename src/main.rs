@@ -1,3 +1,5 @@
+use std::process::ExitCode;
+
 use ui::ui::run;
 pub mod board;
 pub mod defs;
@@ -6,6 +8,14 @@ pub mod extra;
 pub mod movegen;
 pub mod ui;
 
-fn main() {
+// With a subcommand the app runs on the command line, otherwise it opens
+// its window.
+fn main() -> ExitCode {
+    let matches = ui::cli::command().get_matches();
+    if let Some(code) = ui::cli::run(&matches) {
+        return code;
+    }
+
     let _ = run();
+    ExitCode::SUCCESS
 }
@@ -1,7 +1,23 @@
+pub mod adjudication;
+pub mod analysis;
+pub mod chessboard;
+pub mod cli;
 pub mod config;
 pub mod engine;
+pub mod engine_manager;
 pub mod engine_processing;
+pub mod eval_graph;
+pub mod game;
+pub mod notation;
+pub mod openings;
+pub mod persistence;
+pub mod pgn;
 pub mod settings;
+pub mod stats;
 pub mod styling;
+pub mod supervisor;
+pub mod theme;
 pub mod tournament;
+pub mod tournament_tab;
+pub mod uci;
 pub mod ui;
@@ -0,0 +1,216 @@
+// Rules that end engine games early, when the engines agree on how the game
+// will end or it goes on for too long.
+
+use serde::{Deserialize, Serialize};
+
+use super::game::GameTree;
+use super::tournament::GameResult;
+use super::uci::Score;
+
+// A mate counts as a score no engine gives otherwise, in centipawns.
+const MATE_CP: i32 = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Adjudication {
+    // A side loses when both engines scored it resign_score centipawns or
+    // more behind for resign_moves moves each.
+    pub resign: bool,
+    pub resign_moves: u32,
+    pub resign_score: u32,
+    // From move draw_from_move on the game is drawn when both engines kept
+    // the score within draw_score centipawns for draw_moves moves each.
+    pub draw: bool,
+    pub draw_from_move: u32,
+    pub draw_moves: u32,
+    pub draw_score: u32,
+    // Games that reach this many moves are drawn, 0 for no limit.
+    pub max_moves: u32,
+}
+
+impl Default for Adjudication {
+    fn default() -> Self {
+        Self {
+            resign: false,
+            resign_moves: 3,
+            resign_score: 1000,
+            draw: false,
+            draw_from_move: 40,
+            draw_moves: 8,
+            draw_score: 10,
+            max_moves: 0,
+        }
+    }
+}
+
+// How an adjudicated game ends. The termination goes in the PGN.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Verdict {
+    pub result: GameResult,
+    pub termination: &'static str,
+    pub description: String,
+}
+
+impl Adjudication {
+    // The verdict on the game up to its current position, if any rule ends
+    // it. The scores are those the engines left in the game, so a move
+    // without one starts the count over.
+    pub fn adjudicate(&self, game: &GameTree) -> Option<Verdict> {
+        let path = game.path(game.current);
+        let plies = path.len();
+        let move_number = (game.first_ply() + plies) / 2;
+
+        if self.max_moves > 0 && plies >= 2 * self.max_moves as usize {
+            return Some(Verdict {
+                result: GameResult::Draw,
+                termination: "adjudication: move limit",
+                description: format!("Drawn after {} moves", self.max_moves),
+            });
+        }
+
+        // Every played move was searched from the position before it.
+        let played = &path[..plies.saturating_sub(1)];
+        let scores: Vec<Option<i32>> = std::iter::once(0)
+            .chain(played.iter().copied())
+            .map(|node| {
+                let score = game.nodes[node].score?;
+                Some(white_cp(score, game.white_to_move(node)))
+            })
+            .collect();
+
+        let resign_plies = 2 * self.resign_moves.max(1) as usize;
+        let resign_score = self.resign_score as i32;
+        if self.resign {
+            if last_all(&scores, resign_plies, |cp| cp <= -resign_score) {
+                return Some(resigned(GameResult::BlackWins, "White", self));
+            }
+            if last_all(&scores, resign_plies, |cp| cp >= resign_score) {
+                return Some(resigned(GameResult::WhiteWins, "Black", self));
+            }
+        }
+
+        let draw_plies = 2 * self.draw_moves.max(1) as usize;
+        let draw_score = self.draw_score as i32;
+        if self.draw
+            && move_number >= self.draw_from_move as usize
+            && last_all(&scores, draw_plies, |cp| cp.abs() <= draw_score)
+        {
+            return Some(Verdict {
+                result: GameResult::Draw,
+                termination: "adjudication: draw",
+                description: format!(
+                    "Drawn, both engines scored within {} cp for {} moves",
+                    self.draw_score, self.draw_moves
+                ),
+            });
+        }
+
+        None
+    }
+}
+
+fn resigned(result: GameResult, loser: &str, rules: &Adjudication) -> Verdict {
+    Verdict {
+        result,
+        termination: "adjudication: resign",
+        description: format!(
+            "{} resigns, both engines scored it {} cp behind for {} moves",
+            loser, rules.resign_score, rules.resign_moves
+        ),
+    }
+}
+
+// Whether the last count scores are all there and all pass the test.
+fn last_all(scores: &[Option<i32>], count: usize, test: impl Fn(i32) -> bool) -> bool {
+    scores.len() >= count
+        && scores[scores.len() - count..]
+            .iter()
+            .all(|score| score.is_some_and(&test))
+}
+
+// The score in centipawns from white's point of view.
+fn white_cp(score: Score, white_to_move: bool) -> i32 {
+    let cp = match score {
+        Score::Cp(cp) => cp,
+        Score::Mate(moves) if moves > 0 => MATE_CP,
+        Score::Mate(_) => -MATE_CP,
+    };
+
+    if white_to_move {
+        cp
+    } else {
+        -cp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::game::START_FEN;
+    use super::super::notation::uci_to_move;
+    use super::*;
+    use crate::movegen::MoveGenerator;
+
+    // A game of knight moves back and forth, with the scores the engines
+    // gave before each move from the side to move's point of view.
+    fn game(scores: &[i32], start_fen: &str) -> GameTree {
+        let movegen = MoveGenerator::new();
+        let mut game = GameTree::new(start_fen);
+        let moves = ["g1f3", "g8f6", "f3g1", "f6g8"];
+
+        for (ply, &cp) in scores.iter().enumerate() {
+            let board = game.board_at(game.current, &movegen);
+            let m = uci_to_move(&board, &movegen, moves[ply % 4]).unwrap();
+            game.current_node_mut().score = Some(Score::Cp(cp));
+            game.play(m, String::new());
+        }
+
+        game
+    }
+
+    #[test]
+    fn adjudication() {
+        let rules = Adjudication {
+            resign: true,
+            resign_moves: 2,
+            resign_score: 500,
+            draw: true,
+            draw_from_move: 30,
+            draw_moves: 2,
+            draw_score: 10,
+            max_moves: 50,
+        };
+
+        // White's engine sees itself losing and so does black's.
+        let lost = game(&[0, 0, -600, 700, -800, 900], START_FEN);
+        let verdict = rules.adjudicate(&lost).unwrap();
+        assert_eq!(verdict.result, GameResult::BlackWins);
+        assert_eq!(verdict.termination, "adjudication: resign");
+        // Black's engine doesn't agree.
+        assert_eq!(
+            rules.adjudicate(&game(&[-600, 0, -600, 700, -800], START_FEN)),
+            None
+        );
+
+        // Quiet scores only draw late in the game.
+        let quiet = [5, -3, 0, 2];
+        assert_eq!(rules.adjudicate(&game(&quiet, START_FEN)), None);
+        let late = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 30";
+        assert_eq!(
+            rules.adjudicate(&game(&quiet, late)).unwrap().result,
+            GameResult::Draw
+        );
+
+        let limit = Adjudication {
+            max_moves: 2,
+            ..Adjudication::default()
+        };
+        assert_eq!(limit.adjudicate(&game(&[0; 3], START_FEN)), None);
+        assert_eq!(
+            limit
+                .adjudicate(&game(&[0; 4], START_FEN))
+                .unwrap()
+                .termination,
+            "adjudication: move limit"
+        );
+    }
+}
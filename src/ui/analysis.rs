@@ -0,0 +1,207 @@
+use iced::widget::{column, row, Button, Column, Container, Text};
+use iced::{Element, Length, Theme};
+
+use super::notation::uci_line_to_san;
+use super::uci::{Bound, EngineInfo};
+use super::ui::Message;
+use crate::board::Board;
+use crate::defs::Sides;
+use crate::movegen::MoveGenerator;
+
+// A line found by the engine, with its moves in standard algebraic notation.
+#[derive(Debug, Clone)]
+pub struct AnalysisLine {
+    pub info: EngineInfo,
+    pub san: Vec<String>,
+}
+
+// What the engine reported about the position it is thinking on. The search
+// statistics are taken from every info line, the lines only from the ones
+// with a pv.
+#[derive(Debug, Clone, Default)]
+pub struct Analysis {
+    fen: String,
+    white_to_move: bool,
+    first_move_number: usize,
+    pub lines: Vec<AnalysisLine>,
+    pub stats: EngineInfo,
+}
+
+impl Analysis {
+    pub fn update(&mut self, fen: &str, info: EngineInfo, movegen: &MoveGenerator) {
+        if fen != self.fen {
+            self.set_position(fen);
+        }
+
+        self.merge_stats(&info);
+
+        if !info.pv.is_empty() {
+            let mut board = Board::new();
+            if board.read_fen(Some(fen)).is_err() {
+                return;
+            }
+            let index = info.multipv.unwrap_or(1).max(1) as usize - 1;
+            if self.lines.len() <= index {
+                self.lines.resize(
+                    index + 1,
+                    AnalysisLine {
+                        info: EngineInfo::default(),
+                        san: vec![],
+                    },
+                );
+            }
+
+            let san = uci_line_to_san(&board, movegen, &info.pv);
+            self.lines[index] = AnalysisLine { info, san };
+        }
+    }
+
+    // The position the lines are about.
+    pub fn fen(&self) -> &str {
+        &self.fen
+    }
+
+    pub fn clear(&mut self) {
+        self.set_position("");
+    }
+
+    // The best line, which is the first one when the engine sends several.
+    pub fn best(&self) -> Option<&AnalysisLine> {
+        self.lines.first().filter(|line| !line.san.is_empty())
+    }
+
+    // Chance of winning for white in percent and the score as text, after
+    // the best line.
+    pub fn white_eval(&self) -> Option<(f32, String)> {
+        let score = self.best()?.info.score?;
+        Some((
+            score.white_win_percent(self.white_to_move),
+            score.white_text(self.white_to_move),
+        ))
+    }
+
+    fn set_position(&mut self, fen: &str) {
+        let mut fields = fen.split_whitespace().skip(1);
+        self.fen = fen.to_string();
+        self.white_to_move = fields.next() != Some("b");
+        self.first_move_number = fields.nth(3).and_then(|n| n.parse().ok()).unwrap_or(1);
+        self.lines.clear();
+        self.stats = EngineInfo::default();
+    }
+
+    fn merge_stats(&mut self, info: &EngineInfo) {
+        let stats = &mut self.stats;
+        stats.depth = info.depth.or(stats.depth);
+        stats.seldepth = info.seldepth.or(stats.seldepth);
+        stats.nodes = info.nodes.or(stats.nodes);
+        stats.nps = info.nps.or(stats.nps);
+        stats.hashfull = info.hashfull.or(stats.hashfull);
+        stats.tbhits = info.tbhits.or(stats.tbhits);
+        stats.time = info.time.or(stats.time);
+        stats.currmove = info.currmove.clone().or(stats.currmove.take());
+    }
+
+    // The score of a line from white's point of view, like "+0.35" or "-M3".
+    pub fn score_text(&self, info: &EngineInfo) -> String {
+        let Some(score) = info.score else {
+            return String::new();
+        };
+
+        let text = score.white_text(self.white_to_move);
+
+        // Bounds are from the side to move too, so they flip for black.
+        match (info.bound, self.white_to_move) {
+            (Bound::Exact, _) => text,
+            (Bound::Lower, true) | (Bound::Upper, false) => format!(">= {}", text),
+            (Bound::Lower, false) | (Bound::Upper, true) => format!("<= {}", text),
+        }
+    }
+
+    // The moves of a line with move numbers, like "12... Nf6 13. e5".
+    pub fn line_text(&self, line: &AnalysisLine) -> String {
+        let mut text = String::new();
+        let mut move_number = self.first_move_number;
+        let mut side = if self.white_to_move {
+            Sides::WHITE
+        } else {
+            Sides::BLACK
+        };
+
+        for (i, san) in line.san.iter().enumerate() {
+            if side == Sides::WHITE {
+                text.push_str(&format!("{}. ", move_number));
+            } else if i == 0 {
+                text.push_str(&format!("{}... ", move_number));
+            }
+            text.push_str(san);
+            text.push(' ');
+
+            if side == Sides::BLACK {
+                move_number += 1;
+            }
+            side ^= 1;
+        }
+
+        text.trim_end().to_string()
+    }
+
+    pub fn view(&self) -> Element<'_, Message, iced::Renderer<Theme>> {
+        let stats = &self.stats;
+        let depth = match (stats.depth, stats.seldepth) {
+            (Some(depth), Some(seldepth)) => format!("Depth {}/{}", depth, seldepth),
+            (Some(depth), None) => format!("Depth {}", depth),
+            _ => "Depth -".to_string(),
+        };
+        let nps = match stats.nps {
+            Some(nps) if nps >= 1_000_000 => format!("{:.1} Mn/s", nps as f64 / 1_000_000.0),
+            Some(nps) => format!("{} kn/s", nps / 1000),
+            None => "- kn/s".to_string(),
+        };
+        let nodes = stats
+            .nodes
+            .map(|nodes| format!("{} nodes", nodes))
+            .unwrap_or_default();
+        let current = stats
+            .currmove
+            .as_ref()
+            .map(|m| format!("Searching {}", m))
+            .unwrap_or_default();
+
+        // Clicking a line plays its moves on the board.
+        let mut lines = Column::new().spacing(5);
+        for (index, line) in self.lines.iter().enumerate() {
+            lines = lines.push(
+                Button::new(
+                    row![
+                        Text::new(self.score_text(&line.info)).width(70),
+                        Text::new(self.line_text(line)).size(14).width(Length::Fill),
+                    ]
+                    .spacing(10),
+                )
+                .width(Length::Fill)
+                .style(iced::theme::Button::Text)
+                .on_press(Message::PlayLine(index)),
+            );
+        }
+        if self.lines.is_empty() {
+            lines = lines.push(Text::new("No analysis yet").size(14));
+        }
+
+        Container::new(
+            column![
+                row![
+                    Text::new(depth).size(14),
+                    Text::new(nps).size(14),
+                    Text::new(nodes).size(14)
+                ]
+                .spacing(15),
+                Text::new(current).size(12),
+                lines,
+            ]
+            .spacing(8),
+        )
+        .padding(10)
+        .width(Length::Fill)
+        .into()
+    }
+}
@@ -0,0 +1,727 @@
+use iced::advanced::graphics::geometry;
+use iced::advanced::layout::{self, Layout};
+use iced::advanced::renderer::{self, Quad, Renderer as _};
+use iced::advanced::svg::Renderer as _;
+use iced::advanced::text::{self, Renderer as _};
+use iced::advanced::widget::tree::{self, Tree};
+use iced::advanced::{Clipboard, Shell, Widget};
+use iced::alignment::{Horizontal, Vertical};
+use iced::event::{self, Event};
+use iced::widget::canvas::{Frame, Path, Stroke};
+use iced::{keyboard, mouse};
+use iced::{Color, Element, Length, Point, Rectangle, Size, Theme, Vector};
+
+use super::config::{BoardColors, PromotionChoice, Promotions};
+use super::game::{AnnotationColor, Annotations};
+use super::theme::{embedded_svg, PieceSet};
+use super::ui::Message;
+use crate::board::defs::Pieces;
+use crate::board::Board;
+use crate::defs::{Piece, Side, Sides, Square};
+
+type Renderer = iced::Renderer<Theme>;
+
+const PROMOTION_BACKGROUND: Color = Color::from_rgb(0.95, 0.95, 0.95);
+const FILES: [char; 8] = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
+
+// Order in which the promotion picker shows its pieces, starting at the
+// promotion square and moving towards the centre of the board.
+const PROMOTION_OPTIONS: [(Piece, PromotionChoice); 4] = [
+    (Pieces::QUEEN, PromotionChoice::Queen),
+    (Pieces::KNIGHT, PromotionChoice::Knight),
+    (Pieces::ROOK, PromotionChoice::Rook),
+    (Pieces::BISHOP, PromotionChoice::Bishop),
+];
+
+// Squares that get a highlight of their own on top of the square colour.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Highlights {
+    pub last_move: Option<(Square, Square)>,
+    pub check: Option<Square>,
+    pub selected: Option<Square>,
+}
+
+// A piece that is being dragged across the board.
+#[derive(Debug, Clone, Copy)]
+struct Drag {
+    from: Square,
+    side: Side,
+    piece: Piece,
+    cursor: Point,
+}
+
+// An arrow being drawn with the right mouse button.
+#[derive(Debug, Clone, Copy)]
+struct Annotating {
+    from: Square,
+    cursor: Point,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    drag: Option<Drag>,
+    annotating: Option<Annotating>,
+    modifiers: keyboard::Modifiers,
+}
+
+// Annotation color picked with the modifier keys held while right-clicking.
+fn annotation_color(modifiers: keyboard::Modifiers) -> AnnotationColor {
+    if modifiers.shift() {
+        AnnotationColor::Red
+    } else if modifiers.alt() {
+        AnnotationColor::Blue
+    } else if modifiers.control() {
+        AnnotationColor::Yellow
+    } else {
+        AnnotationColor::Green
+    }
+}
+
+// The chess board. Squares, pieces and the promotion picker are drawn by this
+// widget directly, so that pieces can be dragged across the squares and the
+// board scales with the space it is given.
+pub struct ChessBoard<'a> {
+    board: &'a Board,
+    highlighted_squares: &'a [Square],
+    promotion: &'a Promotions,
+    highlights: Highlights,
+    annotations: Option<&'a Annotations>,
+    colors: BoardColors,
+    pieces: Option<&'a PieceSet>,
+    movable_side: Option<Side>,
+    show_coordinates: bool,
+    flipped: bool,
+    width: Length,
+    height: Length,
+}
+
+impl<'a> ChessBoard<'a> {
+    pub fn new(
+        board: &'a Board,
+        highlighted_squares: &'a [Square],
+        promotion: &'a Promotions,
+    ) -> Self {
+        Self {
+            board,
+            highlighted_squares,
+            promotion,
+            highlights: Highlights::default(),
+            annotations: None,
+            colors: BoardColors::default(),
+            pieces: None,
+            movable_side: None,
+            show_coordinates: true,
+            flipped: false,
+            width: Length::Fill,
+            height: Length::Fill,
+        }
+    }
+
+    pub fn highlights(mut self, highlights: Highlights) -> Self {
+        self.highlights = highlights;
+        self
+    }
+
+    // Arrows and circled squares drawn on top of the pieces.
+    pub fn annotations(mut self, annotations: &'a Annotations) -> Self {
+        self.annotations = Some(annotations);
+        self
+    }
+
+    pub fn colors(mut self, colors: BoardColors) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    // Piece set to draw the pieces with, the embedded set if none is given.
+    pub fn pieces(mut self, pieces: &'a PieceSet) -> Self {
+        self.pieces = Some(pieces);
+        self
+    }
+
+    // Only pieces of this side can be picked up and dragged.
+    pub fn movable_side(mut self, side: Option<Side>) -> Self {
+        self.movable_side = side;
+        self
+    }
+
+    pub fn show_coordinates(mut self, show: bool) -> Self {
+        self.show_coordinates = show;
+        self
+    }
+
+    // Render the board from black's perspective.
+    pub fn flipped(mut self, flipped: bool) -> Self {
+        self.flipped = flipped;
+        self
+    }
+
+    pub fn width(mut self, width: impl Into<Length>) -> Self {
+        self.width = width.into();
+        self
+    }
+
+    pub fn height(mut self, height: impl Into<Length>) -> Self {
+        self.height = height.into();
+        self
+    }
+
+    // Top left corner of a square relative to the board.
+    fn square_origin(&self, square: Square, square_size: f32) -> Point {
+        let file = (square % 8) as f32;
+        let rank = (square / 8) as f32;
+
+        if self.flipped {
+            Point::new((7.0 - file) * square_size, rank * square_size)
+        } else {
+            Point::new(file * square_size, (7.0 - rank) * square_size)
+        }
+    }
+
+    fn square_bounds(&self, bounds: Rectangle, square: Square) -> Rectangle {
+        let square_size = bounds.width / 8.0;
+        let origin = self.square_origin(square, square_size);
+
+        Rectangle {
+            x: bounds.x + origin.x,
+            y: bounds.y + origin.y,
+            width: square_size,
+            height: square_size,
+        }
+    }
+
+    fn square_at(&self, bounds: Rectangle, position: Point) -> Option<Square> {
+        if !bounds.contains(position) {
+            return None;
+        }
+
+        let square_size = bounds.width / 8.0;
+        let column = (((position.x - bounds.x) / square_size) as usize).min(7);
+        let row = (((position.y - bounds.y) / square_size) as usize).min(7);
+
+        if self.flipped {
+            Some(row * 8 + (7 - column))
+        } else {
+            Some((7 - row) * 8 + column)
+        }
+    }
+
+    // Squares covered by the promotion picker, paired with the piece that is
+    // shown on them.
+    fn promotion_squares(&self) -> Vec<(Square, PromotionChoice)> {
+        let Some(target) = self.promotion.promotion_square else {
+            return vec![];
+        };
+
+        if !self.promotion.show_promotion_prompt {
+            return vec![];
+        }
+
+        // White promotes on the eighth rank and the picker runs down the file,
+        // black promotes on the first rank and the picker runs up the file.
+        // Either way it extends towards the centre of the board, also when
+        // the board is flipped.
+        let white = target / 8 == 7;
+        PROMOTION_OPTIONS
+            .iter()
+            .enumerate()
+            .map(|(i, (_, choice))| {
+                let square = if white {
+                    target - i * 8
+                } else {
+                    target + i * 8
+                };
+                (square, choice.clone())
+            })
+            .collect()
+    }
+
+    fn draw_piece(&self, renderer: &mut Renderer, side: Side, piece: Piece, bounds: Rectangle) {
+        let handle = match self.pieces {
+            Some(pieces) => pieces.svg(side, piece),
+            None => embedded_svg(side, piece),
+        };
+        renderer.draw(handle, None, bounds);
+    }
+
+    fn square_center(&self, square: Square, square_size: f32) -> Point {
+        let origin = self.square_origin(square, square_size);
+
+        Point::new(origin.x + square_size / 2.0, origin.y + square_size / 2.0)
+    }
+
+    // Draws the annotations, plus the arrow that is being drawn if any, in
+    // coordinates relative to the board.
+    fn draw_annotations(
+        &self,
+        frame: &mut Frame,
+        square_size: f32,
+        annotating: Option<(Annotating, AnnotationColor)>,
+    ) {
+        if let Some(annotations) = self.annotations {
+            for mark in &annotations.squares {
+                let circle = Path::circle(
+                    self.square_center(mark.square, square_size),
+                    square_size * 0.45,
+                );
+                frame.stroke(
+                    &circle,
+                    Stroke::default()
+                        .with_color(mark.color.color())
+                        .with_width(square_size / 16.0),
+                );
+            }
+
+            for arrow in &annotations.arrows {
+                let from = self.square_center(arrow.from, square_size);
+                let to = self.square_center(arrow.to, square_size);
+                draw_arrow(frame, from, to, square_size, arrow.color.color());
+            }
+        }
+
+        if let Some((annotating, color)) = annotating {
+            let from = self.square_center(annotating.from, square_size);
+            draw_arrow(frame, from, annotating.cursor, square_size, color.color());
+        }
+    }
+
+    fn draw_coordinates(&self, renderer: &mut Renderer, bounds: Rectangle) {
+        let square_size = bounds.width / 8.0;
+        let text_size = (square_size / 6.0).max(10.0);
+        let padding = square_size / 20.0;
+
+        for square in 0..64 {
+            let file = square % 8;
+            let rank = square / 8;
+            let square_bounds = self.square_bounds(bounds, square);
+            let light_square = (rank + file) % 2 != 0;
+            let color = if light_square {
+                self.colors.dark_square
+            } else {
+                self.colors.light_square
+            };
+
+            // Rank numbers along the left edge, file letters along the bottom.
+            if square_bounds.x == bounds.x {
+                renderer.fill_text(text::Text {
+                    content: &(rank + 1).to_string(),
+                    bounds: Rectangle {
+                        x: square_bounds.x + padding,
+                        y: square_bounds.y + padding,
+                        ..square_bounds
+                    },
+                    size: text_size,
+                    line_height: text::LineHeight::default(),
+                    color,
+                    font: renderer.default_font(),
+                    horizontal_alignment: Horizontal::Left,
+                    vertical_alignment: Vertical::Top,
+                    shaping: text::Shaping::Basic,
+                });
+            }
+
+            if square_bounds.y + square_size >= bounds.y + bounds.height - 1.0 {
+                renderer.fill_text(text::Text {
+                    content: &FILES[file].to_string(),
+                    bounds: Rectangle {
+                        x: square_bounds.x + square_size - padding,
+                        y: square_bounds.y + square_size - padding,
+                        ..square_bounds
+                    },
+                    size: text_size,
+                    line_height: text::LineHeight::default(),
+                    color,
+                    font: renderer.default_font(),
+                    horizontal_alignment: Horizontal::Right,
+                    vertical_alignment: Vertical::Bottom,
+                    shaping: text::Shaping::Basic,
+                });
+            }
+        }
+    }
+}
+
+impl<'a> Widget<Message, Renderer> for ChessBoard<'a> {
+    fn width(&self) -> Length {
+        self.width
+    }
+
+    fn height(&self) -> Length {
+        self.height
+    }
+
+    fn layout(&self, _renderer: &Renderer, limits: &layout::Limits) -> layout::Node {
+        let max = limits.width(self.width).height(self.height).max();
+        let side = max.width.min(max.height);
+
+        layout::Node::new(Size::new(side, side))
+    }
+
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<State>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(State::default())
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        _renderer: &Renderer,
+        _clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        _viewport: &Rectangle,
+    ) -> event::Status {
+        let state = tree.state.downcast_mut::<State>();
+        let bounds = layout.bounds();
+
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                let Some(position) = cursor.position_over(bounds) else {
+                    return event::Status::Ignored;
+                };
+                let Some(square) = self.square_at(bounds, position) else {
+                    return event::Status::Ignored;
+                };
+
+                // While the promotion picker is open only its pieces respond.
+                if self.promotion.show_promotion_prompt {
+                    if let Some((_, choice)) = self
+                        .promotion_squares()
+                        .into_iter()
+                        .find(|(s, _)| *s == square)
+                    {
+                        shell.publish(Message::PromotionSelected(choice));
+                    }
+                    return event::Status::Captured;
+                }
+
+                shell.publish(Message::SelectSquare(Some(square)));
+
+                let side = self.board.color_on(Some(square));
+                if let Some(piece) = self.board.piece_on(Some(square)) {
+                    if self.movable_side == Some(side) {
+                        state.drag = Some(Drag {
+                            from: square,
+                            side,
+                            piece,
+                            cursor: position,
+                        });
+                    }
+                }
+
+                event::Status::Captured
+            }
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Right)) => {
+                let Some(from) = cursor
+                    .position_over(bounds)
+                    .and_then(|position| self.square_at(bounds, position))
+                else {
+                    return event::Status::Ignored;
+                };
+
+                state.annotating = Some(Annotating {
+                    from,
+                    cursor: cursor.position().unwrap_or_default(),
+                });
+                event::Status::Captured
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Right)) => {
+                let Some(annotating) = state.annotating.take() else {
+                    return event::Status::Ignored;
+                };
+
+                // Releasing on the same square circles it, anywhere else on
+                // the board draws an arrow.
+                let color = annotation_color(state.modifiers);
+                match cursor
+                    .position()
+                    .and_then(|position| self.square_at(bounds, position))
+                {
+                    Some(to) if to == annotating.from => {
+                        shell.publish(Message::ToggleSquareMark(to, color));
+                    }
+                    Some(to) => {
+                        shell.publish(Message::ToggleArrow(annotating.from, to, color));
+                    }
+                    None => {}
+                }
+
+                event::Status::Captured
+            }
+            Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+                state.modifiers = modifiers;
+                event::Status::Ignored
+            }
+            Event::Mouse(mouse::Event::CursorMoved { position }) => {
+                if let Some(annotating) = state.annotating.as_mut() {
+                    annotating.cursor = position;
+                }
+
+                match state.drag.as_mut() {
+                    Some(drag) => {
+                        drag.cursor = position;
+                        event::Status::Captured
+                    }
+                    None => event::Status::Ignored,
+                }
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                let Some(drag) = state.drag.take() else {
+                    return event::Status::Ignored;
+                };
+
+                // Dropping the piece on another square tries to make the move.
+                // If the move is illegal the board is left untouched, so the
+                // piece snaps back to where it came from.
+                if let Some(to) = cursor
+                    .position()
+                    .and_then(|position| self.square_at(bounds, position))
+                {
+                    if to != drag.from {
+                        shell.publish(Message::SelectSquare(Some(to)));
+                    }
+                }
+
+                event::Status::Captured
+            }
+            _ => event::Status::Ignored,
+        }
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        _viewport: &Rectangle,
+        _renderer: &Renderer,
+    ) -> mouse::Interaction {
+        let state = tree.state.downcast_ref::<State>();
+
+        if state.drag.is_some() {
+            mouse::Interaction::Grabbing
+        } else if cursor.is_over(layout.bounds()) {
+            mouse::Interaction::Pointer
+        } else {
+            mouse::Interaction::Idle
+        }
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer,
+        _theme: &Theme,
+        _style: &renderer::Style,
+        layout: Layout<'_>,
+        _cursor: mouse::Cursor,
+        viewport: &Rectangle,
+    ) {
+        let state = tree.state.downcast_ref::<State>();
+        let bounds = layout.bounds();
+        let square_size = bounds.width / 8.0;
+        let dragged_from = state.drag.map(|drag| drag.from);
+
+        for square in 0..64 {
+            let file = square % 8;
+            let rank = square / 8;
+            let square_bounds = self.square_bounds(bounds, square);
+            let light_square = (rank + file) % 2 != 0;
+
+            let color = if light_square {
+                self.colors.light_square
+            } else {
+                self.colors.dark_square
+            };
+            fill_square(renderer, square_bounds, color);
+
+            let last_move = self
+                .highlights
+                .last_move
+                .is_some_and(|(from, to)| square == from || square == to);
+            if last_move {
+                fill_square(renderer, square_bounds, self.colors.last_move);
+            }
+
+            if self.highlights.selected == Some(square) {
+                fill_square(renderer, square_bounds, self.colors.selected);
+            }
+
+            // The king in check gets a rounded glow under the piece.
+            if self.highlights.check == Some(square) {
+                renderer.fill_quad(
+                    Quad {
+                        bounds: square_bounds,
+                        border_radius: (square_size / 2.0).into(),
+                        border_width: 0.0,
+                        border_color: Color::TRANSPARENT,
+                    },
+                    self.colors.check,
+                );
+            }
+
+            if dragged_from == Some(square) {
+                continue;
+            }
+
+            if let Some(piece) = self.board.piece_on(Some(square)) {
+                let side = self.board.color_on(Some(square));
+                self.draw_piece(renderer, side, piece, square_bounds);
+            }
+        }
+
+        // Legal targets: a dot on empty squares, a ring around pieces that
+        // can be captured.
+        for square in self.highlighted_squares {
+            let square_bounds = self.square_bounds(bounds, *square);
+            let occupied = self.board.piece_on(Some(*square)).is_some();
+
+            let (bounds, border_width, background) = if occupied {
+                (square_bounds, square_size / 12.0, Color::TRANSPARENT)
+            } else {
+                let dot = square_size / 3.0;
+                let dot_bounds = Rectangle {
+                    x: square_bounds.center_x() - dot / 2.0,
+                    y: square_bounds.center_y() - dot / 2.0,
+                    width: dot,
+                    height: dot,
+                };
+                (dot_bounds, 0.0, self.colors.legal_move)
+            };
+
+            renderer.fill_quad(
+                Quad {
+                    bounds,
+                    border_radius: (bounds.width / 2.0).into(),
+                    border_width,
+                    border_color: self.colors.legal_move,
+                },
+                background,
+            );
+        }
+
+        if self.show_coordinates {
+            self.draw_coordinates(renderer, bounds);
+        }
+
+        // While an arrow is being drawn it follows the cursor, relative to the
+        // board.
+        let annotating = state.annotating.map(|annotating| {
+            let cursor = Point::new(
+                annotating.cursor.x - bounds.x,
+                annotating.cursor.y - bounds.y,
+            );
+            (
+                Annotating {
+                    cursor,
+                    ..annotating
+                },
+                annotation_color(state.modifiers),
+            )
+        });
+        if annotating.is_some() || self.annotations.is_some_and(|a| !a.is_empty()) {
+            let mut frame = Frame::new(renderer, bounds.size());
+            self.draw_annotations(&mut frame, square_size, annotating);
+
+            renderer.with_translation(Vector::new(bounds.x, bounds.y), |renderer| {
+                geometry::Renderer::draw(renderer, vec![frame.into_geometry()]);
+            });
+        }
+
+        let promotion_squares = self.promotion_squares();
+        if let Some((first, _)) = promotion_squares.first() {
+            let side = if *first / 8 == 7 {
+                Sides::WHITE
+            } else {
+                Sides::BLACK
+            };
+
+            renderer.with_layer(bounds, |renderer| {
+                for ((square, _), (piece, _)) in promotion_squares.iter().zip(PROMOTION_OPTIONS) {
+                    let square_bounds = self.square_bounds(bounds, *square);
+                    renderer.fill_quad(
+                        Quad {
+                            bounds: square_bounds,
+                            border_radius: (square_size / 2.0).into(),
+                            border_width: 0.0,
+                            border_color: Color::TRANSPARENT,
+                        },
+                        PROMOTION_BACKGROUND,
+                    );
+                    self.draw_piece(renderer, side, piece, square_bounds);
+                }
+            });
+        }
+
+        // The dragged piece floats above everything else, centered on the
+        // cursor.
+        if let Some(drag) = state.drag {
+            let floating = Rectangle {
+                x: drag.cursor.x - square_size / 2.0,
+                y: drag.cursor.y - square_size / 2.0,
+                width: square_size,
+                height: square_size,
+            };
+
+            renderer.with_layer(*viewport, |renderer| {
+                self.draw_piece(renderer, drag.side, drag.piece, floating);
+            });
+        }
+    }
+}
+
+// An arrow from the center of one square to another, with its head ending
+// just short of the center of the target square.
+fn draw_arrow(frame: &mut Frame, from: Point, to: Point, square_size: f32, color: Color) {
+    let delta = to - from;
+    let length = (delta.x * delta.x + delta.y * delta.y).sqrt();
+    if length < square_size / 2.0 {
+        return;
+    }
+
+    let direction = Vector::new(delta.x / length, delta.y / length);
+    let normal = Vector::new(-direction.y, direction.x);
+    let head_length = square_size * 0.4;
+    let head_width = square_size * 0.4;
+    let tip = to - direction * (square_size * 0.1);
+    let base = tip - direction * head_length;
+
+    let shaft = Path::line(from, base);
+    frame.stroke(
+        &shaft,
+        Stroke::default()
+            .with_color(color)
+            .with_width(square_size * 0.15),
+    );
+
+    let head = Path::new(|builder| {
+        builder.move_to(tip);
+        builder.line_to(base + normal * (head_width / 2.0));
+        builder.line_to(base - normal * (head_width / 2.0));
+        builder.close();
+    });
+    frame.fill(&head, color);
+}
+
+fn fill_square(renderer: &mut Renderer, bounds: Rectangle, color: Color) {
+    renderer.fill_quad(
+        Quad {
+            bounds,
+            border_radius: 0.0.into(),
+            border_width: 0.0,
+            border_color: Color::TRANSPARENT,
+        },
+        color,
+    );
+}
+
+impl<'a> From<ChessBoard<'a>> for Element<'a, Message, Renderer> {
+    fn from(board: ChessBoard<'a>) -> Self {
+        Self::new(board)
+    }
+}
@@ -0,0 +1,702 @@
+// Plays tournaments between engines without the board, like cutechess-cli.
+// Games are played side by side, and the results are printed as they come
+// in.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use tokio::task::JoinSet;
+
+use super::adjudication::Adjudication;
+use super::config::{Clock, EngineDefinition, TimeControl, TimeControlKind};
+use super::engine::{EngineProcess, UIengine};
+use super::engine_processing::{quit, read_bestmove, start_engine, write};
+use super::game::{can_mate, draw_by_rule, GameTree, START_FEN};
+use super::notation::{move_to_san, uci_to_move};
+use super::openings::{self, Opening, OpeningOrder};
+use super::persistence;
+use super::pgn;
+use super::stats::Sprt;
+use super::supervisor::{diagnose, EngineError};
+use super::tournament::{GameResult, Tournament, TournamentFormat};
+use super::uci::{search_time_limit, EngineInfo};
+use crate::board::Board;
+use crate::defs::Sides;
+use crate::movegen::MoveGenerator;
+
+// The command line. Without a subcommand the app opens its window.
+pub fn command() -> Command {
+    Command::new("chess-ui").subcommand(
+        Command::new("tournament")
+            .about("Play a tournament between engines without the board")
+            .arg(
+                Arg::new("engine")
+                    .short('e')
+                    .long("engine")
+                    .action(ArgAction::Append)
+                    .required(true)
+                    .help("A registered engine by name, or the path to an engine"),
+            )
+            .arg(
+                Arg::new("format")
+                    .long("format")
+                    .value_parser(["round-robin", "gauntlet", "swiss"])
+                    .default_value("round-robin"),
+            )
+            .arg(
+                Arg::new("rounds")
+                    .short('r')
+                    .long("rounds")
+                    .value_parser(value_parser!(usize))
+                    .default_value("1"),
+            )
+            .arg(
+                Arg::new("tc").long("tc").help(
+                    "Time control in seconds: [moves/]time[+increment], e.g. 40/60 or 10+0.1",
+                ),
+            )
+            .arg(
+                Arg::new("st")
+                    .long("st")
+                    .conflicts_with("tc")
+                    .help("Seconds for every move"),
+            )
+            .arg(
+                Arg::new("concurrency")
+                    .short('c')
+                    .long("concurrency")
+                    .value_parser(value_parser!(usize))
+                    .default_value("1"),
+            )
+            .arg(
+                Arg::new("openings")
+                    .long("openings")
+                    .help("EPD or PGN file with the openings to start from"),
+            )
+            .arg(
+                Arg::new("plies")
+                    .long("plies")
+                    .value_parser(value_parser!(usize))
+                    .default_value("8")
+                    .help("Plies taken from the games of a PGN opening file"),
+            )
+            .arg(
+                Arg::new("order")
+                    .long("order")
+                    .value_parser(["sequential", "random"])
+                    .default_value("sequential"),
+            )
+            .arg(
+                Arg::new("seed")
+                    .long("seed")
+                    .value_parser(value_parser!(u64))
+                    .default_value("1"),
+            )
+            .arg(
+                Arg::new("sprt")
+                    .long("sprt")
+                    .help("Stop when the SPRT decides, e.g. elo0=0,elo1=5,alpha=0.05,beta=0.05"),
+            )
+            .arg(
+                Arg::new("resign")
+                    .long("resign")
+                    .help("Adjudicate a loss when both engines agree, e.g. movecount=3,score=1000"),
+            )
+            .arg(
+                Arg::new("draw")
+                    .long("draw")
+                    .help("Adjudicate a draw when both engines agree, e.g. movenumber=40,movecount=8,score=10"),
+            )
+            .arg(
+                Arg::new("maxmoves")
+                    .long("maxmoves")
+                    .value_parser(value_parser!(u32))
+                    .help("Draw games that reach this many moves"),
+            )
+            .arg(
+                Arg::new("pgnout")
+                    .long("pgnout")
+                    .help("File the games are added to"),
+            ),
+    )
+}
+
+// Run the subcommand that was given, or None to open the window.
+pub fn run(matches: &ArgMatches) -> Option<ExitCode> {
+    let (_, matches) = matches
+        .subcommand()
+        .filter(|(name, _)| *name == "tournament")?;
+
+    let options = match TournamentOptions::from_matches(matches) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            return Some(ExitCode::FAILURE);
+        }
+    };
+    let runtime = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("Could not start the runtime: {}", e);
+            return Some(ExitCode::FAILURE);
+        }
+    };
+
+    Some(match runtime.block_on(run_tournament(options)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    })
+}
+
+// Everything a tournament on the command line is played with.
+struct TournamentOptions {
+    tournament: Tournament,
+    engines: Vec<EngineDefinition>,
+    time_control: TimeControl,
+    safety_margin_ms: u64,
+    adjudication: Adjudication,
+    concurrency: usize,
+    pgn_out: Option<PathBuf>,
+}
+
+impl TournamentOptions {
+    fn from_matches(matches: &ArgMatches) -> Result<Self, String> {
+        let config = persistence::load();
+
+        let engines = matches
+            .get_many::<String>("engine")
+            .into_iter()
+            .flatten()
+            .map(|engine| find_engine(engine, &config.engines))
+            .collect::<Result<Vec<_>, _>>()?;
+        if engines.len() < 2 {
+            return Err("A tournament needs at least two engines".to_string());
+        }
+
+        let format = match matches.get_one::<String>("format").map(String::as_str) {
+            Some("gauntlet") => TournamentFormat::Gauntlet,
+            Some("swiss") => TournamentFormat::Swiss,
+            _ => TournamentFormat::RoundRobin,
+        };
+        let rounds = matches.get_one::<usize>("rounds").copied().unwrap_or(1);
+        let names = engines.iter().map(|engine| engine.name.clone()).collect();
+        let mut tournament = Tournament::new(format, names, rounds.max(1));
+
+        if let Some(sprt) = matches.get_one::<String>("sprt") {
+            tournament.sprt = Some(parse_sprt(sprt)?);
+        }
+        if let Some(file) = matches.get_one::<String>("openings") {
+            let plies = matches.get_one::<usize>("plies").copied().unwrap_or(8);
+            let order = match matches.get_one::<String>("order").map(String::as_str) {
+                Some("random") => OpeningOrder::Random,
+                _ => OpeningOrder::Sequential,
+            };
+            let seed = matches.get_one::<u64>("seed").copied().unwrap_or(1);
+            tournament.set_openings(openings::load(Path::new(file), plies)?, order, seed);
+        }
+
+        // Without a time control the one of the app is used.
+        let time_control = match (
+            matches.get_one::<String>("tc"),
+            matches.get_one::<String>("st"),
+        ) {
+            (Some(tc), _) => parse_time_control(tc)?,
+            (None, Some(st)) => TimeControl {
+                kind: TimeControlKind::FixedPerMove,
+                move_time_ms: parse_seconds(st)?,
+                ..TimeControl::default()
+            },
+            (None, None) => config.time_control,
+        };
+
+        // Only the rules given on the command line end games early.
+        let mut adjudication = Adjudication::default();
+        if let Some(resign) = matches.get_one::<String>("resign") {
+            adjudication.resign = true;
+            for (name, value) in parse_settings(resign)? {
+                match name {
+                    "movecount" => adjudication.resign_moves = value as u32,
+                    "score" => adjudication.resign_score = value as u32,
+                    _ => return Err(format!("Invalid resign setting: {}", name)),
+                }
+            }
+        }
+        if let Some(draw) = matches.get_one::<String>("draw") {
+            adjudication.draw = true;
+            for (name, value) in parse_settings(draw)? {
+                match name {
+                    "movenumber" => adjudication.draw_from_move = value as u32,
+                    "movecount" => adjudication.draw_moves = value as u32,
+                    "score" => adjudication.draw_score = value as u32,
+                    _ => return Err(format!("Invalid draw setting: {}", name)),
+                }
+            }
+        }
+        if let Some(&max_moves) = matches.get_one::<u32>("maxmoves") {
+            adjudication.max_moves = max_moves;
+        }
+
+        Ok(Self {
+            tournament,
+            engines,
+            time_control,
+            safety_margin_ms: config.safety_margin_ms,
+            adjudication,
+            concurrency: matches
+                .get_one::<usize>("concurrency")
+                .copied()
+                .unwrap_or(1)
+                .max(1),
+            pgn_out: matches.get_one::<String>("pgnout").map(PathBuf::from),
+        })
+    }
+}
+
+// An engine registered in the app, with its options and timeouts, or else an
+// engine file with the default settings.
+fn find_engine(engine: &str, registered: &[EngineDefinition]) -> Result<EngineDefinition, String> {
+    if let Some(definition) = registered
+        .iter()
+        .find(|definition| definition.name == engine)
+    {
+        return Ok(definition.clone());
+    }
+
+    let path = PathBuf::from(engine);
+    if !path.is_file() {
+        return Err(format!("{} is not a registered engine or a file", engine));
+    }
+    Ok(EngineDefinition {
+        name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| engine.to_string()),
+        path,
+        ..EngineDefinition::default()
+    })
+}
+
+// A time control like cutechess-cli takes it, in seconds: "40/60" is 60
+// seconds for every 40 moves, "10+0.1" is 10 seconds with 0.1 added after
+// every move and "60" is a minute for the game.
+pub fn parse_time_control(text: &str) -> Result<TimeControl, String> {
+    let (moves, time) = match text.split_once('/') {
+        Some((moves, time)) => {
+            let moves = moves
+                .trim()
+                .parse::<u32>()
+                .map_err(|_| format!("Invalid number of moves in time control {}", text))?;
+            (Some(moves), time)
+        }
+        None => (None, text),
+    };
+    let (base, increment) = match time.split_once('+') {
+        Some((base, increment)) => (parse_seconds(base)?, parse_seconds(increment)?),
+        None => (parse_seconds(time)?, 0),
+    };
+
+    let kind = match moves {
+        Some(moves) if moves > 0 => TimeControlKind::MovesPerPeriod,
+        _ if increment > 0 => TimeControlKind::Increment,
+        _ => TimeControlKind::SuddenDeath,
+    };
+    Ok(TimeControl {
+        kind,
+        base_ms: base,
+        increment_ms: increment,
+        moves_per_period: moves.unwrap_or(0),
+        ..TimeControl::default()
+    })
+}
+
+// Seconds, which may have a fraction, as milliseconds.
+fn parse_seconds(text: &str) -> Result<u64, String> {
+    match text.trim().parse::<f64>() {
+        Ok(seconds) if seconds >= 0.0 => Ok((seconds * 1000.0).round() as u64),
+        _ => Err(format!("Invalid number of seconds: {}", text)),
+    }
+}
+
+// Settings given as name=value pairs separated by commas, with numbers for
+// values.
+fn parse_settings(text: &str) -> Result<Vec<(&str, f64)>, String> {
+    text.split(',')
+        .filter(|pair| !pair.trim().is_empty())
+        .map(|pair| {
+            let invalid = || format!("Invalid setting: {}", pair);
+            let (name, value) = pair.split_once('=').ok_or_else(invalid)?;
+            let value = value.trim().parse::<f64>().map_err(|_| invalid())?;
+            Ok((name.trim(), value))
+        })
+        .collect()
+}
+
+// SPRT bounds as name=value pairs. Those that are left out keep their
+// default.
+pub fn parse_sprt(text: &str) -> Result<Sprt, String> {
+    let mut sprt = Sprt::default();
+
+    for (name, value) in parse_settings(text)? {
+        match name {
+            "elo0" => sprt.elo0 = value,
+            "elo1" => sprt.elo1 = value,
+            // Error rates have to be a chance, but not a certainty.
+            "alpha" if value > 0.0 && value < 1.0 => sprt.alpha = value,
+            "beta" if value > 0.0 && value < 1.0 => sprt.beta = value,
+            _ => return Err(format!("Invalid SPRT setting: {}={}", name, value)),
+        }
+    }
+
+    Ok(sprt)
+}
+
+// Play the games of the tournament, as many at a time as the concurrency
+// allows, and print every result with the statistics so far.
+async fn run_tournament(options: TournamentOptions) -> Result<(), String> {
+    let TournamentOptions {
+        mut tournament,
+        engines,
+        time_control,
+        safety_margin_ms,
+        adjudication,
+        concurrency,
+        pgn_out,
+    } = options;
+
+    println!(
+        "{} tournament, {} rounds: {}",
+        tournament.format,
+        tournament.rounds,
+        tournament.engines.join(", ")
+    );
+    if let Some(sprt) = tournament.sprt_summary() {
+        println!("{}", sprt);
+    }
+
+    let movegen = Arc::new(MoveGenerator::new());
+    let mut playing = vec![];
+    let mut games = JoinSet::new();
+    loop {
+        while playing.len() < concurrency {
+            let Some(index) = tournament.next_game_besides(&playing) else {
+                break;
+            };
+            let pairing = &tournament.games[index];
+            let setup = GameSetup {
+                index,
+                round: pairing.round,
+                white: engines[pairing.white].clone(),
+                black: engines[pairing.black].clone(),
+                opening: tournament.opening(index).cloned(),
+                time_control,
+                safety_margin_ms,
+                adjudication,
+            };
+            println!(
+                "Started game {} of {} ({} vs {})",
+                index + 1,
+                tournament.games.len(),
+                setup.white.name,
+                setup.black.name
+            );
+            games.spawn(play_game(setup, movegen.clone()));
+            playing.push(index);
+        }
+
+        let Some(finished) = games.join_next().await else {
+            break;
+        };
+        let played = finished.map_err(|e| format!("A game was lost: {}", e))?;
+        playing.retain(|&index| index != played.index);
+        tournament.record(played.index, played.result);
+
+        let pairing = &tournament.games[played.index];
+        println!(
+            "Finished game {} ({} vs {}): {} {{{}}}",
+            played.index + 1,
+            tournament.engines[pairing.white],
+            tournament.engines[pairing.black],
+            played.result.pgn(),
+            played.description
+        );
+        for (title, stats) in tournament.test_results() {
+            println!("{}: {}", title, stats.summary());
+        }
+        if let Some(sprt) = tournament.sprt_summary() {
+            println!("{}", sprt);
+        }
+
+        if let Some(path) = &pgn_out {
+            let pgn = pgn::export(&played.game);
+            let written = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut file| writeln!(file, "{}\n", pgn.trim_end()));
+            if let Err(e) = written {
+                eprintln!("Could not write the game to {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    match tournament.sprt_result() {
+        Some(result) => println!(
+            "\nStopped after {} games, SPRT {}",
+            tournament.games_played(),
+            result
+        ),
+        None => println!(
+            "\nTournament over after {} games",
+            tournament.games_played()
+        ),
+    }
+    println!("{}", tournament.crosstable());
+
+    Ok(())
+}
+
+// A game of the tournament as it is handed to the task that plays it.
+struct GameSetup {
+    index: usize,
+    round: usize,
+    white: EngineDefinition,
+    black: EngineDefinition,
+    opening: Option<Opening>,
+    time_control: TimeControl,
+    safety_margin_ms: u64,
+    adjudication: Adjudication,
+}
+
+struct PlayedGame {
+    index: usize,
+    result: GameResult,
+    description: String,
+    game: GameTree,
+}
+
+// Play a game between two new engine processes. An engine that can't be
+// started, stops answering or plays an illegal move loses.
+async fn play_game(setup: GameSetup, movegen: Arc<MoveGenerator>) -> PlayedGame {
+    let start = Opening {
+        name: String::new(),
+        fen: START_FEN.to_string(),
+        moves: vec![],
+    };
+    let opening = setup.opening.as_ref().unwrap_or(&start);
+    let (mut game, mut board) = openings::start_game(opening, &movegen);
+
+    game.set_header("Event", "Engine tournament");
+    game.set_header("Round", &(setup.round + 1).to_string());
+    game.set_header("White", &setup.white.name);
+    game.set_header("Black", &setup.black.name);
+    if let Some(opening) = &setup.opening {
+        game.set_header("Opening", &opening.name);
+    }
+
+    let fen = board.create_fen();
+    let mut players = vec![];
+    for (side, definition) in [(Sides::WHITE, &setup.white), (Sides::BLACK, &setup.black)] {
+        match start_player(definition, &fen).await {
+            Ok(player) => players.push(player),
+            Err(e) => {
+                for player in &mut players {
+                    let _ = quit(&mut player.process).await;
+                }
+                return finish(setup.index, game, loss_of(side), "abandoned", e.to_string());
+            }
+        }
+    }
+
+    let mut clock = Clock::with_time_control(&setup.time_control);
+    clock.start(board.side_to_move());
+    let (result, termination, description) = loop {
+        let side = board.side_to_move();
+        let name = if side == Sides::WHITE {
+            &setup.white.name
+        } else {
+            &setup.black.name
+        };
+
+        if board.legal_moves(&movegen).is_empty() {
+            let king = board.king_square(side);
+            if movegen.square_attacked(&board, side ^ 1, king) {
+                break (loss_of(side), "normal", format!("{} is mated", name));
+            }
+            break (GameResult::Draw, "normal", "Stalemate".to_string());
+        }
+        if let Some(rule) = draw_by_rule(&board) {
+            break (GameResult::Draw, "normal", format!("Draw by {}", rule));
+        }
+
+        let player = &mut players[side];
+        let go = clock.go_command(side, setup.safety_margin_ms);
+        let commands = format!("{}\n{}\n", game.uci_position(game.current), go);
+        let limit = search_time_limit(&go, side == Sides::WHITE)
+            .map(|ms| Duration::from_millis(ms) + player.move_grace);
+        let response = match write(&mut player.process, &commands).await {
+            Ok(()) => read_bestmove(player, None, limit).await,
+            Err(e) => Err(e),
+        };
+        clock.press(side);
+
+        let response = match response {
+            Ok(response) => response,
+            Err(EngineError::Timeout(_)) => break time_forfeit(&board, side, name),
+            Err(e) => {
+                let e = diagnose(e, &mut player.process, &player.stderr).await;
+                break (loss_of(side), "abandoned", format!("{}: {}", name, e));
+            }
+        };
+        if clock.flagged == Some(side) {
+            break time_forfeit(&board, side, name);
+        }
+
+        let last = response.last().map(|line| line.trim()).unwrap_or_default();
+        let bestmove = last.split_whitespace().nth(1).unwrap_or_default();
+        let Some(m) = uci_to_move(&board, &movegen, bestmove) else {
+            break (
+                loss_of(side),
+                "rules infraction",
+                format!("{} played the illegal move {}", name, bestmove),
+            );
+        };
+
+        // The score is about the position the engine searched.
+        if let Some(score) = response
+            .iter()
+            .rev()
+            .filter_map(|line| EngineInfo::parse(line))
+            .find_map(|info| info.score)
+        {
+            game.current_node_mut().score = Some(score);
+        }
+        let san = move_to_san(&board, &movegen, m);
+        board.make_move(m, &movegen);
+        game.play(m, san);
+
+        if let Some(verdict) = setup.adjudication.adjudicate(&game) {
+            break (verdict.result, verdict.termination, verdict.description);
+        }
+    };
+
+    for player in &mut players {
+        let _ = quit(&mut player.process).await;
+    }
+    finish(setup.index, game, result, termination, description)
+}
+
+// Start an engine for a game, with the options and timeouts it was
+// registered with.
+async fn start_player(
+    definition: &EngineDefinition,
+    fen: &str,
+) -> Result<EngineProcess, EngineError> {
+    let mut engine = UIengine::new(String::new(), 0);
+    engine.set_timeouts(definition);
+
+    let (process, receiver, _sender, stderr) = start_engine(
+        &definition.path,
+        fen,
+        &definition.option_values,
+        engine.handshake_timeout,
+    )
+    .await?;
+    Ok(EngineProcess {
+        process,
+        search_up_to: 0,
+        move_grace: engine.move_grace,
+        receiver,
+        stderr,
+        ponder: false,
+        pondering: None,
+    })
+}
+
+// The end of a game the side ran out of time in, which only loses when the
+// opponent can still mate.
+fn time_forfeit(board: &Board, side: usize, name: &str) -> (GameResult, &'static str, String) {
+    if can_mate(board, side ^ 1) {
+        return (
+            loss_of(side),
+            "time forfeit",
+            format!("{} lost on time", name),
+        );
+    }
+
+    (
+        GameResult::Draw,
+        "time forfeit",
+        format!("{} ran out of time, but can't be mated", name),
+    )
+}
+
+fn loss_of(side: usize) -> GameResult {
+    if side == Sides::WHITE {
+        GameResult::BlackWins
+    } else {
+        GameResult::WhiteWins
+    }
+}
+
+fn finish(
+    index: usize,
+    mut game: GameTree,
+    result: GameResult,
+    termination: &str,
+    description: String,
+) -> PlayedGame {
+    game.set_header("Result", result.pgn());
+    game.set_header("Termination", termination);
+
+    PlayedGame {
+        index,
+        result,
+        description,
+        game,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_controls_and_sprt() {
+        let tc = parse_time_control("40/60").unwrap();
+        assert_eq!(tc.kind, TimeControlKind::MovesPerPeriod);
+        assert_eq!((tc.base_ms, tc.moves_per_period), (60_000, 40));
+
+        let tc = parse_time_control("10+0.1").unwrap();
+        assert_eq!(tc.kind, TimeControlKind::Increment);
+        assert_eq!((tc.base_ms, tc.increment_ms), (10_000, 100));
+
+        assert_eq!(
+            parse_time_control("300").unwrap().kind,
+            TimeControlKind::SuddenDeath
+        );
+        assert!(parse_time_control("x/60").is_err());
+        assert!(parse_time_control("60+").is_err());
+
+        let sprt = parse_sprt("elo0=-2, elo1=3,alpha=0.1").unwrap();
+        assert_eq!((sprt.elo0, sprt.elo1, sprt.alpha), (-2.0, 3.0, 0.1));
+        assert_eq!(sprt.beta, Sprt::default().beta);
+        assert!(parse_sprt("alpha=1").is_err());
+        assert!(parse_sprt("gamma=1").is_err());
+        assert_eq!(
+            parse_settings("movecount=3, score=600").unwrap(),
+            [("movecount", 3.0), ("score", 600.0)]
+        );
+        assert!(parse_settings("movecount").is_err());
+    }
+}
@@ -1,14 +1,41 @@
+use std::path::PathBuf;
 use std::time::Instant;
 
-use crate::defs::{Sides, Square};
+use iced::Color;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+use super::adjudication::Adjudication;
+use super::openings::OpeningOrder;
+use super::stats::Sprt;
+use super::theme::UiTheme;
+use super::tournament::TournamentFormat;
+use super::uci::EngineOption;
+use crate::defs::{Side, Sides, Square};
+
+// The settings the user can change. They are stored in the config file, so
+// missing fields fall back to their default.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct UIConfig {
     pub show_coordinates: bool,
     pub flip_board: bool,
     pub search_depth: u32,
     pub game_mode: GameMode,
     pub player_side: u32,
+    pub theme: UiTheme,
+    pub engines: Vec<EngineDefinition>,
+    pub engine_slots: EngineSlots,
+    pub time_control: TimeControl,
+    // How far playing engines search a move.
+    pub search_limit: SearchLimit,
+    pub search_nodes: u64,
+    pub search_move_time_ms: u64,
+    // Taken off the time given to an engine, for the time it takes to send
+    // the move between the processes.
+    pub safety_margin_ms: u64,
+    // Number of lines the analysis engine shows.
+    pub multipv: u32,
+    pub tournament: TournamentConfig,
 }
 
 impl ::std::default::Default for UIConfig {
@@ -17,8 +44,265 @@ impl ::std::default::Default for UIConfig {
             show_coordinates: true,
             flip_board: false,
             search_depth: 3,
-            game_mode: GameMode::PlayerPlayer,
+            game_mode: GameMode::EngineEngine,
             player_side: 0,
+            theme: UiTheme::default(),
+            engines: vec![],
+            engine_slots: EngineSlots::default(),
+            time_control: TimeControl::default(),
+            search_limit: SearchLimit::Clock,
+            search_nodes: 1_000_000,
+            search_move_time_ms: 1000,
+            safety_margin_ms: 50,
+            multipv: 3,
+            tournament: TournamentConfig::default(),
+        }
+    }
+}
+
+pub const DEFAULT_HANDSHAKE_TIMEOUT_MS: u64 = 3000;
+pub const DEFAULT_MOVE_GRACE_MS: u64 = 2000;
+
+// A registered engine, with what it told about itself when it was added and
+// the option values the user picked for it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EngineDefinition {
+    pub name: String,
+    pub author: String,
+    pub path: PathBuf,
+    pub options: Vec<EngineOption>,
+    pub option_values: Vec<(String, String)>,
+    // How long the engine may take to answer uci and isready.
+    pub handshake_timeout_ms: u64,
+    // Time a search may run over its time limit before the engine is told
+    // to stop.
+    pub move_grace_ms: u64,
+}
+
+impl Default for EngineDefinition {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            author: String::new(),
+            path: PathBuf::new(),
+            options: vec![],
+            option_values: vec![],
+            handshake_timeout_ms: DEFAULT_HANDSHAKE_TIMEOUT_MS,
+            move_grace_ms: DEFAULT_MOVE_GRACE_MS,
+        }
+    }
+}
+
+impl EngineDefinition {
+    // The value picked by the user, or the engine's default.
+    pub fn option_value(&self, option: &EngineOption) -> String {
+        self.option_values
+            .iter()
+            .find(|(name, _)| *name == option.name)
+            .map(|(_, value)| value.clone())
+            .or_else(|| option.default.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn set_option_value(&mut self, name: &str, value: String) {
+        match self.option_values.iter_mut().find(|(n, _)| n == name) {
+            Some(option) => option.1 = value,
+            None => self.option_values.push((name.to_string(), value)),
+        }
+    }
+}
+
+// Names of the registered engines assigned to play white, black or to
+// analyse.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EngineSlots {
+    pub white: Option<String>,
+    pub black: Option<String>,
+    pub analysis: Option<String>,
+}
+
+// The tournament the user set up last. Engines are kept by name.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TournamentConfig {
+    pub format: TournamentFormat,
+    pub engines: Vec<String>,
+    pub rounds: usize,
+    // Stop when the SPRT decided whether the first engine is stronger.
+    pub sprt_enabled: bool,
+    pub sprt: Sprt,
+    // An EPD or PGN file with openings. Without one games start from the
+    // start position.
+    pub opening_file: String,
+    // Plies taken from the games of a PGN suite.
+    pub opening_plies: usize,
+    pub opening_order: OpeningOrder,
+    pub opening_seed: u64,
+    // Ends engine games early, in and out of tournaments.
+    pub adjudication: Adjudication,
+}
+
+impl Default for TournamentConfig {
+    fn default() -> Self {
+        Self {
+            format: TournamentFormat::RoundRobin,
+            engines: vec![],
+            rounds: 2,
+            sprt_enabled: false,
+            sprt: Sprt::default(),
+            opening_file: String::new(),
+            opening_plies: 8,
+            opening_order: OpeningOrder::Sequential,
+            opening_seed: 1,
+            adjudication: Adjudication::default(),
+        }
+    }
+}
+
+// What the go command sent to a playing engine limits its search by.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum SearchLimit {
+    // The time left on the clocks, with increments and moves to go.
+    Clock,
+    MoveTime,
+    Nodes,
+    Depth,
+}
+
+impl SearchLimit {
+    pub const ALL: [SearchLimit; 4] = [
+        SearchLimit::Clock,
+        SearchLimit::MoveTime,
+        SearchLimit::Nodes,
+        SearchLimit::Depth,
+    ];
+}
+
+impl std::fmt::Display for SearchLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                SearchLimit::Clock => "Clock",
+                SearchLimit::MoveTime => "Time per move",
+                SearchLimit::Nodes => "Nodes",
+                SearchLimit::Depth => "Depth",
+            }
+        )
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum TimeControlKind {
+    // One amount of time for the whole game.
+    SuddenDeath,
+    // Time for the game plus an increment for every move.
+    Increment,
+    // The base time is given again every so many moves.
+    MovesPerPeriod,
+    // A fixed amount of time for every move.
+    FixedPerMove,
+}
+
+impl TimeControlKind {
+    pub const ALL: [TimeControlKind; 4] = [
+        TimeControlKind::SuddenDeath,
+        TimeControlKind::Increment,
+        TimeControlKind::MovesPerPeriod,
+        TimeControlKind::FixedPerMove,
+    ];
+}
+
+impl std::fmt::Display for TimeControlKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                TimeControlKind::SuddenDeath => "Sudden death",
+                TimeControlKind::Increment => "Increment",
+                TimeControlKind::MovesPerPeriod => "Moves per period",
+                TimeControlKind::FixedPerMove => "Time per move",
+            }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeControl {
+    pub kind: TimeControlKind,
+    pub base_ms: u64,
+    pub increment_ms: u64,
+    pub moves_per_period: u32,
+    pub move_time_ms: u64,
+}
+
+impl Default for TimeControl {
+    fn default() -> Self {
+        Self {
+            kind: TimeControlKind::Increment,
+            base_ms: 5 * 60 * 1000,
+            increment_ms: 0,
+            moves_per_period: 40,
+            move_time_ms: 10 * 1000,
+        }
+    }
+}
+
+impl TimeControl {
+    // Time on the clock at the start of the game.
+    pub fn start_time(&self) -> u64 {
+        match self.kind {
+            TimeControlKind::FixedPerMove => self.move_time_ms,
+            _ => self.base_ms,
+        }
+    }
+
+    pub fn increment(&self) -> u64 {
+        match self.kind {
+            TimeControlKind::Increment | TimeControlKind::MovesPerPeriod => self.increment_ms,
+            _ => 0,
+        }
+    }
+
+    // Moves left until the next period for a side that made the given number
+    // of moves, if the time control has periods.
+    pub fn moves_to_go(&self, moves_made: u32) -> Option<u32> {
+        match self.kind {
+            TimeControlKind::MovesPerPeriod if self.moves_per_period > 0 => {
+                Some(self.moves_per_period - moves_made % self.moves_per_period)
+            }
+            _ => None,
+        }
+    }
+}
+
+// Colours used to draw the board and the highlights on top of it. The
+// highlight colours are drawn over the square colour, so they should be
+// translucent.
+#[derive(Debug, Clone, Copy)]
+pub struct BoardColors {
+    pub light_square: Color,
+    pub dark_square: Color,
+    pub last_move: Color,
+    pub selected: Color,
+    pub check: Color,
+    pub legal_move: Color,
+}
+
+impl Default for BoardColors {
+    fn default() -> Self {
+        Self {
+            light_square: Color::from_rgb(0.91, 0.741, 0.529),
+            dark_square: Color::from_rgb(0.639, 0.502, 0.329),
+            last_move: Color::from_rgba(0.8, 0.82, 0.2, 0.5),
+            selected: Color::from_rgba(0.08, 0.47, 0.3, 0.5),
+            check: Color::from_rgba(0.9, 0.1, 0.1, 0.7),
+            legal_move: Color::from_rgba(0.08, 0.33, 0.2, 0.5),
         }
     }
 }
@@ -48,7 +332,7 @@ pub enum PromotionChoice {
     Knight,
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum GameMode {
     PlayerPlayer,
     PlayerEngine,
@@ -81,19 +365,242 @@ pub enum GameTab {
     UpdateTime(Sides),
 }
 
+// A chess clock with a time per side. Only the clock of the side to move
+// runs, and pressing it after a move starts the other one.
 #[derive(Debug, Clone, Copy)]
 pub struct Clock {
+    pub time_control: TimeControl,
     pub wtime: u128,
     pub btime: u128,
+    pub running: bool,
+    pub side: Side,
+    // Moves made by white and black.
+    pub moves: [u32; 2],
+    pub flagged: Option<Side>,
     pub last_tick: Instant,
 }
 
 impl Clock {
     pub fn new() -> Self {
+        Clock::with_time_control(&TimeControl::default())
+    }
+
+    pub fn with_time_control(time_control: &TimeControl) -> Self {
         Self {
-            wtime: 5 * 60 * 1000, // Example: 5 minutes in milliseconds
-            btime: 5 * 60 * 1000, // Example: 5 minutes in milliseconds
+            time_control: *time_control,
+            wtime: time_control.start_time() as u128,
+            btime: time_control.start_time() as u128,
+            running: false,
+            side: Sides::WHITE,
+            moves: [0, 0],
+            flagged: None,
             last_tick: Instant::now(),
         }
     }
+
+    pub fn remaining(&self, side: Side) -> u128 {
+        if side == Sides::WHITE {
+            self.wtime
+        } else {
+            self.btime
+        }
+    }
+
+    fn remaining_mut(&mut self, side: Side) -> &mut u128 {
+        if side == Sides::WHITE {
+            &mut self.wtime
+        } else {
+            &mut self.btime
+        }
+    }
+
+    // Start the clock of the given side.
+    pub fn start(&mut self, side: Side) {
+        self.side = side;
+        self.running = self.flagged.is_none();
+        self.last_tick = Instant::now();
+    }
+
+    pub fn stop(&mut self) {
+        self.tick();
+        self.running = false;
+    }
+
+    // Take the time since the last tick off the running clock, and stop it
+    // when the flag falls.
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_tick).as_millis();
+        self.last_tick = now;
+        if !self.running {
+            return;
+        }
+
+        let side = self.side;
+        let remaining = self.remaining_mut(side);
+        *remaining = remaining.saturating_sub(elapsed);
+        if *remaining == 0 {
+            self.running = false;
+            self.flagged = Some(side);
+        }
+    }
+
+    // Press the clock after the side made a move: its time is updated for
+    // the time control and the clock of the other side starts. A move made
+    // after the flag fell doesn't count.
+    pub fn press(&mut self, side: Side) {
+        if self.running && self.side == side {
+            self.tick();
+        }
+        if self.flagged.is_some() {
+            return;
+        }
+
+        self.moves[side] += 1;
+        let time_control = self.time_control;
+        let moves = self.moves[side];
+        let remaining = self.remaining_mut(side);
+        *remaining += time_control.increment() as u128;
+        match time_control.kind {
+            TimeControlKind::FixedPerMove => *remaining = time_control.move_time_ms as u128,
+            TimeControlKind::MovesPerPeriod
+                if time_control.moves_per_period > 0
+                    && moves.is_multiple_of(time_control.moves_per_period) =>
+            {
+                *remaining += time_control.base_ms as u128
+            }
+            _ => (),
+        }
+
+        self.start(side ^ 1);
+    }
+
+    // The go command for the side to move with the time left on the clocks.
+    // The margin is taken off the times so the engine answers before its
+    // flag falls.
+    pub fn go_command(&self, side: Side, margin_ms: u64) -> String {
+        let margin = margin_ms as u128;
+        let time_control = &self.time_control;
+
+        if time_control.kind == TimeControlKind::FixedPerMove {
+            let movetime = self.remaining(side).saturating_sub(margin).max(1);
+            return format!("go movetime {}", movetime);
+        }
+
+        let mut go = format!(
+            "go wtime {} btime {}",
+            self.wtime.saturating_sub(margin).max(1),
+            self.btime.saturating_sub(margin).max(1)
+        );
+        let increment = time_control.increment();
+        if increment > 0 {
+            go.push_str(&format!(" winc {} binc {}", increment, increment));
+        }
+        if let Some(moves_to_go) = time_control.moves_to_go(self.moves[side]) {
+            go.push_str(&format!(" movestogo {}", moves_to_go));
+        }
+
+        go
+    }
+}
+
+// Clock time as minutes and seconds, with tenths of a second in the last ten
+// seconds.
+pub fn format_clock(ms: u128) -> String {
+    if ms < 10_000 {
+        format!("{}.{}", ms / 1000, (ms % 1000) / 100)
+    } else {
+        format!("{}:{:02}", ms / 60000, (ms % 60000) / 1000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn go_commands_follow_the_time_control() {
+        let mut time_control = TimeControl {
+            kind: TimeControlKind::MovesPerPeriod,
+            base_ms: 60_000,
+            increment_ms: 1000,
+            moves_per_period: 40,
+            move_time_ms: 5000,
+        };
+        let mut clock = Clock::with_time_control(&time_control);
+        clock.moves = [3, 2];
+        assert_eq!(
+            clock.go_command(Sides::BLACK, 50),
+            "go wtime 59950 btime 59950 winc 1000 binc 1000 movestogo 38"
+        );
+
+        time_control.kind = TimeControlKind::SuddenDeath;
+        let clock = Clock::with_time_control(&time_control);
+        assert_eq!(
+            clock.go_command(Sides::WHITE, 0),
+            "go wtime 60000 btime 60000"
+        );
+
+        time_control.kind = TimeControlKind::FixedPerMove;
+        let clock = Clock::with_time_control(&time_control);
+        assert_eq!(clock.go_command(Sides::WHITE, 100), "go movetime 4900");
+    }
+
+    #[test]
+    fn pressing_the_clock_follows_the_time_control() {
+        let mut time_control = TimeControl {
+            kind: TimeControlKind::Increment,
+            base_ms: 60_000,
+            increment_ms: 1000,
+            moves_per_period: 2,
+            move_time_ms: 5000,
+        };
+
+        // The clocks are stopped, so no time is taken off between presses.
+        let mut clock = Clock::with_time_control(&time_control);
+        clock.press(Sides::WHITE);
+        assert_eq!((clock.wtime, clock.btime), (61_000, 60_000));
+        assert_eq!(clock.side, Sides::BLACK);
+        assert_eq!(clock.moves, [1, 0]);
+
+        // The base time comes back every moves_per_period moves.
+        time_control.kind = TimeControlKind::MovesPerPeriod;
+        let mut clock = Clock::with_time_control(&time_control);
+        clock.press(Sides::WHITE);
+        assert_eq!(clock.wtime, 61_000);
+        clock.press(Sides::WHITE);
+        assert_eq!(clock.wtime, 122_000);
+
+        time_control.kind = TimeControlKind::FixedPerMove;
+        let mut clock = Clock::with_time_control(&time_control);
+        clock.wtime = 1200;
+        clock.press(Sides::WHITE);
+        assert_eq!(clock.wtime, 5000);
+    }
+
+    #[test]
+    fn the_flag_falls_when_the_time_runs_out() {
+        let mut clock = Clock::new();
+        clock.start(Sides::WHITE);
+        clock.wtime = 5;
+        clock.last_tick -= std::time::Duration::from_millis(10);
+        clock.tick();
+        assert_eq!(clock.wtime, 0);
+        assert_eq!(clock.flagged, Some(Sides::WHITE));
+        assert!(!clock.running);
+
+        // A move made after the flag fell gets no time and doesn't count.
+        clock.press(Sides::WHITE);
+        assert_eq!(clock.wtime, 0);
+        assert_eq!(clock.moves, [0, 0]);
+        clock.start(Sides::BLACK);
+        assert!(!clock.running);
+    }
+
+    #[test]
+    fn clock_text() {
+        assert_eq!(format_clock(9_950), "9.9");
+        assert_eq!(format_clock(10_000), "0:10");
+        assert_eq!(format_clock(3_725_000), "62:05");
+    }
 }
@@ -1,4 +1,3 @@
-use std::error::Error;
 use std::path::PathBuf;
 use std::time::Duration;
 
@@ -7,10 +6,12 @@ use iced::{subscription, Subscription};
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::{Child, ChildStdout};
 use tokio::sync::mpsc::Receiver;
-use tokio::time::{self};
+use tokio::time::{self, Instant};
 
-use super::config::Clock;
-use super::engine_processing::{handle_engine_thinking, start_engine};
+use super::config::{EngineDefinition, DEFAULT_HANDSHAKE_TIMEOUT_MS, DEFAULT_MOVE_GRACE_MS};
+use super::engine_processing::{handle_engine_thinking, run_analysis, start_engine};
+use super::supervisor::{EngineError, StderrLog};
+use super::uci::{EngineInfo, EngineOption};
 use super::ui::Message;
 
 #[derive(Debug, PartialEq)]
@@ -19,21 +20,63 @@ pub enum EngineStatus {
     TurnedOn,
 }
 
-pub struct Engine1;
-pub struct Engine2;
+// The roles a registered engine can be assigned to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EngineSlot {
+    White,
+    Black,
+    Analysis,
+}
+
+impl EngineSlot {
+    pub const ALL: [EngineSlot; 3] = [EngineSlot::White, EngineSlot::Black, EngineSlot::Analysis];
+}
+
+impl std::fmt::Display for EngineSlot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                EngineSlot::White => "White",
+                EngineSlot::Black => "Black",
+                EngineSlot::Analysis => "Analysis",
+            }
+        )
+    }
+}
 
 pub enum EngineState {
     Start(UIengine),
-    Thinking(Child, u32, Receiver<String>),
+    Thinking(EngineProcess),
     TurnedOff,
 }
 
+// A running engine that plays in a game.
+pub struct EngineProcess {
+    pub process: Child,
+    pub search_up_to: u32,
+    pub move_grace: Duration,
+    pub receiver: Receiver<String>,
+    pub stderr: StderrLog,
+    // Whether the engine searches the expected reply on the opponent's time.
+    pub ponder: bool,
+    // The position line of the ponder search that is running.
+    pub pondering: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct UIengine {
     pub engine_path: PathBuf,
     pub search_up_to: u32,
     pub position: String,
-    pub clock: Clock,
+    // Option values set with setoption when the engine starts.
+    pub options: Vec<(String, String)>,
+    pub handshake_timeout: Duration,
+    pub move_grace: Duration,
+    // Counts restarts after a crash or a change of engine. It is part of the
+    // subscription's id, so a restart starts a new process.
+    pub restarts: u32,
 }
 
 impl UIengine {
@@ -43,11 +86,34 @@ impl UIengine {
             search_up_to: depth,
             //position: String::from("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
             position: String::from("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
-            clock: Clock::new(),
+            options: vec![],
+            handshake_timeout: Duration::from_millis(DEFAULT_HANDSHAKE_TIMEOUT_MS),
+            move_grace: Duration::from_millis(DEFAULT_MOVE_GRACE_MS),
+            restarts: 0,
         }
     }
 
-    pub fn run_engine(self, channel_id: std::any::TypeId) -> Subscription<Message> {
+    // The timeouts picked for the engine. A handshake gets at least a
+    // little time, even when the field was cleared.
+    pub fn set_timeouts(&mut self, definition: &EngineDefinition) {
+        self.handshake_timeout = Duration::from_millis(definition.handshake_timeout_ms.max(100));
+        self.move_grace = Duration::from_millis(definition.move_grace_ms);
+    }
+
+    // Pondering is switched on with the engine's own Ponder option.
+    pub fn ponders(&self) -> bool {
+        self.options.iter().any(|(name, value)| {
+            name.eq_ignore_ascii_case("Ponder") && value.eq_ignore_ascii_case("true")
+        })
+    }
+
+    // The engine runs for as long as the subscription is alive. The slot and
+    // path identify it, so assigning another engine starts a new process.
+    // When the engine fails the UI is told, and the process stays down until
+    // it is restarted.
+    pub fn run_engine(self, slot: EngineSlot) -> Subscription<Message> {
+        let channel_id = (slot, self.engine_path.clone(), self.restarts);
+
         subscription::channel(channel_id, 100, move |mut output| {
             let engine1 = self.clone();
 
@@ -55,10 +121,10 @@ impl UIengine {
                 let mut state1 = EngineState::Start(engine1.clone());
 
                 loop {
-                    state1 = match run_single_engine(state1, &engine1, &mut output).await {
+                    state1 = match run_single_engine(slot, state1, &mut output).await {
                         Ok(new_state) => new_state,
                         Err(e) => {
-                            eprintln!("Engine 1 encountered an error: {}", e);
+                            let _ = output.try_send(Message::EngineFailed(slot, e));
                             EngineState::TurnedOff
                         }
                     };
@@ -66,32 +132,56 @@ impl UIengine {
             }
         })
     }
+
+    // The analysis engine keeps searching the position on the board until
+    // the subscription is dropped.
+    pub fn run_analysis(self) -> Subscription<Message> {
+        let channel_id = (EngineSlot::Analysis, self.engine_path.clone());
+
+        subscription::channel(channel_id, 100, move |mut output| {
+            let engine = self.clone();
+
+            async move {
+                if let Err(e) = run_analysis(&engine, &mut output).await {
+                    let _ = output.try_send(Message::EngineFailed(EngineSlot::Analysis, e));
+                }
+                let _ = output.try_send(Message::AnalysisStopped);
+
+                loop {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                }
+            }
+        })
+    }
 }
 
 async fn run_single_engine(
+    slot: EngineSlot,
     mut state: EngineState,
-    engine: &UIengine,
     output: &mut Sender<Message>,
-) -> Result<EngineState, Box<dyn Error>> {
+) -> Result<EngineState, EngineError> {
     match &mut state {
         EngineState::Start(engine) => {
-            match start_engine(&engine.engine_path, &engine.position, engine.search_up_to).await {
-                Ok((process, receiver, sender)) => {
-                    output.try_send(Message::EngineReady(sender))?;
-                    Ok(EngineState::Thinking(
-                        process,
-                        engine.search_up_to,
-                        receiver,
-                    ))
-                }
-                Err(e) => {
-                    eprintln!("Failed to start engine: {}", e);
-                    Ok(EngineState::TurnedOff)
-                }
-            }
+            let (process, receiver, sender, stderr) = start_engine(
+                &engine.engine_path,
+                &engine.position,
+                &engine.options,
+                engine.handshake_timeout,
+            )
+            .await?;
+            let _ = output.try_send(Message::EngineReady(slot, sender));
+            Ok(EngineState::Thinking(EngineProcess {
+                process,
+                search_up_to: engine.search_up_to,
+                move_grace: engine.move_grace,
+                receiver,
+                stderr,
+                ponder: engine.ponders(),
+                pondering: None,
+            }))
         }
-        EngineState::Thinking(process, search_up_to, receiver) => {
-            handle_engine_thinking(process, *search_up_to, receiver, output, &engine.clock).await?;
+        EngineState::Thinking(engine) => {
+            handle_engine_thinking(engine, output).await?;
             Ok(state)
         }
         EngineState::TurnedOff => {
@@ -104,17 +194,23 @@ async fn run_single_engine(
 pub async fn read_setup_from_process(
     mut reader: BufReader<&mut ChildStdout>,
     mut buffer_str: &mut String,
+    timeout: Duration,
 ) -> bool {
+    let deadline = Instant::now() + timeout;
     loop {
         let read_line_result = async {
             reader
                 .read_line(&mut buffer_str)
                 .await
-                .map(|_| buffer_str.clone())
+                .map(|read| (read, buffer_str.clone()))
         };
 
-        match time::timeout(Duration::from_millis(3000), read_line_result).await {
-            Ok(Ok(line)) => {
+        match time::timeout_at(deadline, read_line_result).await {
+            Ok(Ok((0, _))) => {
+                eprintln!("Engine exited during setup");
+                break false;
+            }
+            Ok(Ok((_, line))) => {
                 if line.contains("uciok") || line.contains("readyok") {
                     buffer_str.clear();
                     return true;
@@ -132,36 +228,86 @@ pub async fn read_setup_from_process(
     }
 }
 
+// Read the answer to uci up to uciok, keeping the options the engine
+// reports. None if the engine doesn't finish in time.
+pub async fn read_options_from_process(
+    mut reader: BufReader<&mut ChildStdout>,
+    buffer_str: &mut String,
+    timeout: Duration,
+) -> Option<Vec<EngineOption>> {
+    let deadline = Instant::now() + timeout;
+    let mut options = vec![];
+    loop {
+        buffer_str.clear();
+        match time::timeout_at(deadline, reader.read_line(buffer_str)).await {
+            Ok(Ok(0)) => {
+                eprintln!("Engine exited before uciok");
+                return None;
+            }
+            Ok(Ok(_)) => {
+                let line = buffer_str.trim();
+                if line == "uciok" {
+                    buffer_str.clear();
+                    return Some(options);
+                }
+                if let Some(option) = EngineOption::parse(line) {
+                    options.push(option);
+                }
+            }
+            Ok(Err(e)) => {
+                eprintln!("Error reading line: {:?}", e);
+                return None;
+            }
+            Err(e) => {
+                eprintln!("Timeout occurred: {}", e);
+                return None;
+            }
+        }
+    }
+}
+
+// Read the engine's output up to its bestmove. Info lines are passed on to
+// the UI as they come in, together with the position they are about, unless
+// the search is thrown away.
+// Without a time limit the engine may search for as long as it likes.
 pub async fn read_moves_from_process(
     mut reader: BufReader<&mut ChildStdout>,
     mut buffer_str: &mut String,
-) -> Result<Vec<String>, Box<dyn Error>> {
+    mut info: Option<(&str, &mut Sender<Message>)>,
+    limit: Option<Duration>,
+) -> Result<Vec<String>, EngineError> {
+    // Far enough away to never be reached.
+    let deadline = Instant::now() + limit.unwrap_or(Duration::from_secs(60 * 60 * 24 * 365));
     let mut lines = Vec::new();
     loop {
         let read_line_result = async {
             reader
                 .read_line(&mut buffer_str)
                 .await
-                .map(|_| buffer_str.clone())
+                .map(|read| (read, buffer_str.clone()))
         };
 
-        match time::timeout(Duration::from_millis(7000), read_line_result).await {
-            Ok(Ok(line)) => {
+        match time::timeout_at(deadline, read_line_result).await {
+            Ok(Ok((0, _))) => {
+                return Err(EngineError::Io("engine closed its output".into()));
+            }
+            Ok(Ok((_, line))) => {
                 buffer_str.clear();
+                if let (Some((fen, output)), Some(engine_info)) =
+                    (info.as_mut(), EngineInfo::parse(&line))
+                {
+                    // A full channel only drops an update, the next one
+                    // replaces it anyway.
+                    let _ = output.try_send(Message::EngineInfo(fen.to_string(), engine_info));
+                }
                 if line.contains("bestmove") {
                     lines.push(line);
                     break;
                 }
                 lines.push(line);
             }
-            Ok(Err(e)) => {
-                eprintln!("Error reading line: {:?}", e);
-                return Err(Box::new(e)); // Return an error with more information
-            }
-            Err(e) => {
-                eprintln!("Timeout occurred: {}", e);
-                return Err(Box::new(e));
-            }
+            Ok(Err(e)) => return Err(e.into()),
+            Err(_) => return Err(EngineError::Timeout("no bestmove in time".into())),
         }
     }
 
@@ -0,0 +1,397 @@
+use std::path::PathBuf;
+
+use iced::widget::{
+    column, row, Button, Checkbox, Column, Container, PickList, Scrollable, Text, TextInput,
+};
+use iced::{alignment, Alignment, Command, Element, Length, Theme};
+use iced_aw::TabLabel;
+
+use super::config::{EngineDefinition, EngineSlots};
+use super::engine::EngineSlot;
+use super::engine_processing::probe_engine;
+use super::uci::{EngineOption, UiElement};
+use super::ui::{Message, Tab};
+
+#[derive(Clone, Debug)]
+pub enum EngineManagerMessage {
+    PathChanged(String),
+    AddEngine,
+    EngineProbed(Result<EngineDefinition, String>),
+    RemoveEngine(usize),
+    AssignSlot(EngineSlot, String),
+    ShowOptions(Option<usize>),
+    SetOption(usize, String, String),
+    PressButton(usize, String),
+    ResetOptions(usize),
+    SetHandshakeTimeout(usize, String),
+    SetMoveGrace(usize, String),
+}
+
+// The registered engines and which of them play white, black or analyse.
+pub struct EngineManager {
+    pub engines: Vec<EngineDefinition>,
+    pub slots: EngineSlots,
+    new_path: String,
+    status: String,
+    // The engine whose options are shown in the options form.
+    selected: Option<usize>,
+}
+
+impl EngineManager {
+    pub fn new(engines: Vec<EngineDefinition>, slots: EngineSlots) -> Self {
+        Self {
+            engines,
+            slots,
+            new_path: String::new(),
+            status: String::new(),
+            selected: None,
+        }
+    }
+
+    pub fn update(&mut self, message: EngineManagerMessage) -> Command<Message> {
+        match message {
+            EngineManagerMessage::PathChanged(path) => {
+                self.new_path = path;
+                Command::none()
+            }
+            EngineManagerMessage::AddEngine => {
+                let path = PathBuf::from(self.new_path.trim());
+                self.status = format!("Starting {}...", path.display());
+
+                Command::perform(probe_engine(path), |result| {
+                    Message::EngineManager(EngineManagerMessage::EngineProbed(result))
+                })
+            }
+            EngineManagerMessage::EngineProbed(Ok(mut engine)) => {
+                engine.name = self.unique_name(&engine.name);
+                self.status = format!("Added {}", engine.name);
+                self.new_path.clear();
+
+                // The first engines added fill the empty slots.
+                for slot in EngineSlot::ALL {
+                    if self.slot(slot).is_none() {
+                        *self.slot_mut(slot) = Some(engine.name.clone());
+                    }
+                }
+                self.engines.push(engine);
+                Command::none()
+            }
+            EngineManagerMessage::EngineProbed(Err(e)) => {
+                self.status = e;
+                Command::none()
+            }
+            EngineManagerMessage::RemoveEngine(index) => {
+                if index < self.engines.len() {
+                    self.selected = None;
+                    let removed = self.engines.remove(index);
+                    for slot in EngineSlot::ALL {
+                        if self.slot(slot) == Some(&removed.name) {
+                            *self.slot_mut(slot) = None;
+                        }
+                    }
+                }
+                Command::none()
+            }
+            EngineManagerMessage::AssignSlot(slot, name) => {
+                *self.slot_mut(slot) = Some(name);
+                Command::none()
+            }
+            EngineManagerMessage::ShowOptions(index) => {
+                self.selected = index.filter(|&index| index < self.engines.len());
+                Command::none()
+            }
+            EngineManagerMessage::SetOption(index, name, value) => {
+                if let Some(engine) = self.engines.get_mut(index) {
+                    engine.set_option_value(&name, value);
+                }
+                Command::none()
+            }
+            EngineManagerMessage::PressButton(index, name) => {
+                // Buttons trigger an action in a running engine, so they are
+                // sent right away instead of being stored.
+                let Some(engine) = self.engines.get(index) else {
+                    return Command::none();
+                };
+                let Some(command) = engine
+                    .options
+                    .iter()
+                    .find(|option| option.name == name)
+                    .and_then(|option| option.setoption(""))
+                else {
+                    return Command::none();
+                };
+
+                let path = engine.path.clone();
+                Command::perform(async move { (path, command) }, |(path, command)| {
+                    Message::SendToEngine(path, command)
+                })
+            }
+            EngineManagerMessage::ResetOptions(index) => {
+                if let Some(engine) = self.engines.get_mut(index) {
+                    engine.option_values.clear();
+                }
+                Command::none()
+            }
+            EngineManagerMessage::SetHandshakeTimeout(index, value) => {
+                if let Some(engine) = self.engines.get_mut(index) {
+                    engine.handshake_timeout_ms = parse_millis(&value);
+                }
+                Command::none()
+            }
+            EngineManagerMessage::SetMoveGrace(index, value) => {
+                if let Some(engine) = self.engines.get_mut(index) {
+                    engine.move_grace_ms = parse_millis(&value);
+                }
+                Command::none()
+            }
+        }
+    }
+
+    // The engine assigned to the slot, if it is still registered.
+    pub fn engine_in(&self, slot: EngineSlot) -> Option<&EngineDefinition> {
+        let name = self.slot(slot)?;
+        self.engines.iter().find(|engine| &engine.name == name)
+    }
+
+    pub fn engine_named(&self, name: &str) -> Option<&EngineDefinition> {
+        self.engines.iter().find(|engine| engine.name == name)
+    }
+
+    pub fn engine_names(&self) -> Vec<String> {
+        self.engines
+            .iter()
+            .map(|engine| engine.name.clone())
+            .collect()
+    }
+
+    pub fn engine_path(&self, slot: EngineSlot) -> PathBuf {
+        self.engine_in(slot)
+            .map(|engine| engine.path.clone())
+            .unwrap_or_default()
+    }
+
+    // The option values the user picked for the engine in the slot.
+    pub fn option_values(&self, slot: EngineSlot) -> Vec<(String, String)> {
+        self.engine_in(slot)
+            .map(|engine| engine.option_values.clone())
+            .unwrap_or_default()
+    }
+
+    fn slot(&self, slot: EngineSlot) -> Option<&String> {
+        match slot {
+            EngineSlot::White => self.slots.white.as_ref(),
+            EngineSlot::Black => self.slots.black.as_ref(),
+            EngineSlot::Analysis => self.slots.analysis.as_ref(),
+        }
+    }
+
+    fn slot_mut(&mut self, slot: EngineSlot) -> &mut Option<String> {
+        match slot {
+            EngineSlot::White => &mut self.slots.white,
+            EngineSlot::Black => &mut self.slots.black,
+            EngineSlot::Analysis => &mut self.slots.analysis,
+        }
+    }
+
+    // Engines are told apart by name, so a second build of the same engine
+    // gets a number added to its name.
+    fn unique_name(&self, name: &str) -> String {
+        let mut unique = name.to_string();
+        let mut count = 1;
+        while self.engines.iter().any(|engine| engine.name == unique) {
+            count += 1;
+            unique = format!("{} ({})", name, count);
+        }
+
+        unique
+    }
+}
+
+impl Tab for EngineManager {
+    type Message = Message;
+
+    fn title(&self) -> String {
+        "Engines".to_string()
+    }
+
+    fn tab_label(&self) -> TabLabel {
+        TabLabel::Text(self.title())
+    }
+
+    fn content(&self) -> Element<'_, Message, iced::Renderer<Theme>> {
+        let add_row = row![
+            TextInput::new("path to engine", &self.new_path)
+                .on_input(EngineManagerMessage::PathChanged)
+                .on_submit(EngineManagerMessage::AddEngine),
+            Button::new(Text::new("Add")).on_press(EngineManagerMessage::AddEngine),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+        let names: Vec<String> = self.engines.iter().map(|e| e.name.clone()).collect();
+        let mut slots = Column::new().spacing(10);
+        for slot in EngineSlot::ALL {
+            slots = slots.push(
+                row![
+                    Text::new(format!("{}: ", slot)).width(80),
+                    PickList::new(names.clone(), self.slot(slot).cloned(), move |name| {
+                        EngineManagerMessage::AssignSlot(slot, name)
+                    })
+                    .placeholder("none"),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+            );
+        }
+
+        let mut engines = Column::new().spacing(10);
+        for (index, engine) in self.engines.iter().enumerate() {
+            let author = if engine.author.is_empty() {
+                String::new()
+            } else {
+                format!(" by {}", engine.author)
+            };
+
+            engines = engines.push(
+                row![
+                    column![
+                        Text::new(format!("{}{}", engine.name, author)),
+                        Text::new(engine.path.to_string_lossy().to_string()).size(12),
+                    ]
+                    .width(Length::Fill),
+                    Button::new(Text::new("Options"))
+                        .on_press(EngineManagerMessage::ShowOptions(Some(index))),
+                    Button::new(Text::new("Remove"))
+                        .on_press(EngineManagerMessage::RemoveEngine(index)),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+            );
+        }
+
+        let mut content = column![add_row, Text::new(&self.status).size(14), slots, engines]
+            .spacing(15)
+            .padding(10);
+        if let Some(index) = self.selected {
+            content = content.push(self.options_form(index));
+        }
+
+        let content: Element<EngineManagerMessage, iced::Renderer<Theme>> =
+            Container::new(Scrollable::new(content))
+                .align_x(alignment::Horizontal::Center)
+                .height(Length::Fill)
+                .width(Length::Fill)
+                .into();
+
+        content.map(Message::EngineManager)
+    }
+}
+
+impl EngineManager {
+    // A form with a widget for every option the engine reported.
+    fn options_form(
+        &self,
+        index: usize,
+    ) -> Element<'_, EngineManagerMessage, iced::Renderer<Theme>> {
+        let engine = &self.engines[index];
+
+        let mut form = Column::new().spacing(10).push(
+            row![
+                Text::new(format!("{} options", engine.name))
+                    .size(20)
+                    .width(Length::Fill),
+                Button::new(Text::new("Defaults"))
+                    .on_press(EngineManagerMessage::ResetOptions(index)),
+                Button::new(Text::new("Close")).on_press(EngineManagerMessage::ShowOptions(None)),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+        );
+
+        // Timeouts are the GUI's own settings for the engine, not UCI
+        // options.
+        form = form
+            .push(
+                row![
+                    Text::new("Handshake timeout (ms)").width(150),
+                    TextInput::new("3000", &engine.handshake_timeout_ms.to_string())
+                        .on_input(move |value| {
+                            EngineManagerMessage::SetHandshakeTimeout(index, value)
+                        })
+                        .width(120),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+            )
+            .push(
+                row![
+                    Text::new("Move grace (ms)").width(150),
+                    TextInput::new("2000", &engine.move_grace_ms.to_string())
+                        .on_input(move |value| EngineManagerMessage::SetMoveGrace(index, value))
+                        .width(120),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+            );
+
+        if engine.options.is_empty() {
+            form = form.push(Text::new("This engine has no options").size(14));
+        }
+
+        for option in &engine.options {
+            let value = engine.option_value(option);
+            form = form.push(
+                row![
+                    Text::new(&option.name).width(150),
+                    option_widget(index, option, value)
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+            );
+        }
+
+        form.into()
+    }
+}
+
+// Digits typed in a time field, where an empty field counts as zero.
+fn parse_millis(value: &str) -> u64 {
+    value
+        .chars()
+        .filter(|c| c.is_ascii_digit())
+        .collect::<String>()
+        .parse()
+        .unwrap_or(0)
+}
+
+fn option_widget(
+    index: usize,
+    option: &EngineOption,
+    value: String,
+) -> Element<'static, EngineManagerMessage, iced::Renderer<Theme>> {
+    let name = option.name.clone();
+    let set = move |value: String| EngineManagerMessage::SetOption(index, name.clone(), value);
+
+    match &option.ui_element {
+        UiElement::Spin { min, max } => row![
+            TextInput::new("", &value).on_input(set).width(120),
+            Text::new(format!("{} - {}", min, max)).size(12),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center)
+        .into(),
+        UiElement::Check => {
+            Checkbox::new("", value == "true", move |checked| set(checked.to_string()))
+                .size(20)
+                .into()
+        }
+        UiElement::Combo(vars) => PickList::new(vars.clone(), Some(value), set).into(),
+        UiElement::Button => Button::new(Text::new("Send"))
+            .on_press(EngineManagerMessage::PressButton(
+                index,
+                option.name.clone(),
+            ))
+            .into(),
+        UiElement::String => TextInput::new("", &value).on_input(set).into(),
+    }
+}
@@ -1,29 +1,94 @@
-use std::error::Error;
 use std::path::PathBuf;
 use std::process::Stdio;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use iced::futures::channel::mpsc::Sender;
-use tokio::io::{AsyncWriteExt, BufReader};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::process::{Child, Command};
 use tokio::time::timeout;
 
-use crate::extra::parse::algebraic_square_to_number;
-
-use super::config::{Clock, PromotionChoice};
-use super::engine::{read_moves_from_process, read_setup_from_process, UIengine};
+use super::config::{EngineDefinition, DEFAULT_HANDSHAKE_TIMEOUT_MS};
+use super::engine::{
+    read_moves_from_process, read_options_from_process, read_setup_from_process, EngineProcess,
+    UIengine,
+};
+use super::supervisor::{diagnose, EngineError, StderrLog};
+use super::uci::{
+    ponder_command, search_time_limit, with_moves, EngineInfo, EngineOption, UiElement,
+};
 use super::ui::Message;
+
+// Start the engine once to ask for its name, author and options, then quit
+// it again.
+pub async fn probe_engine(path: PathBuf) -> Result<EngineDefinition, String> {
+    let mut process = Command::new(&path)
+        .kill_on_drop(true)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Could not start {}: {}", path.display(), e))?;
+
+    let mut definition = EngineDefinition {
+        name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        path: path.clone(),
+        ..EngineDefinition::default()
+    };
+
+    let mut stdin = process.stdin.take().ok_or("Failed to get stdin")?;
+    stdin.write_all(b"uci\n").await.map_err(|e| e.to_string())?;
+    stdin.flush().await.map_err(|e| e.to_string())?;
+
+    let stdout = process.stdout.take().ok_or("Failed to get stdout")?;
+    let mut lines = BufReader::new(stdout).lines();
+    loop {
+        let handshake = Duration::from_millis(DEFAULT_HANDSHAKE_TIMEOUT_MS);
+        let line = match timeout(handshake, lines.next_line()).await {
+            Ok(Ok(Some(line))) => line,
+            Ok(Ok(None)) => return Err(format!("{} exited before uciok", path.display())),
+            Ok(Err(e)) => return Err(e.to_string()),
+            Err(_) => return Err(format!("{} did not answer uci", path.display())),
+        };
+
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix("id name ") {
+            definition.name = name.trim().to_string();
+        } else if let Some(author) = line.strip_prefix("id author ") {
+            definition.author = author.trim().to_string();
+        } else if let Some(option) = EngineOption::parse(line) {
+            definition.options.push(option);
+        } else if line == "uciok" {
+            break;
+        }
+    }
+
+    let _ = stdin.write_all(b"quit\n").await;
+    let _ = stdin.flush().await;
+    if timeout(Duration::from_millis(1000), process.wait())
+        .await
+        .is_err()
+    {
+        let _ = process.kill().await;
+    }
+
+    Ok(definition)
+}
+
 pub async fn start_engine(
     engine_path: &PathBuf,
     position: &str,
-    search_up_to: u32,
+    option_values: &[(String, String)],
+    handshake_timeout: Duration,
 ) -> Result<
     (
         Child,
         tokio::sync::mpsc::Receiver<String>,
         tokio::sync::mpsc::Sender<String>,
+        StderrLog,
     ),
-    Box<dyn Error>,
+    EngineError,
 > {
     let (sender, receiver): (
         tokio::sync::mpsc::Sender<String>,
@@ -33,192 +98,430 @@ pub async fn start_engine(
     let mut cmd = Command::new(engine_path);
     cmd.kill_on_drop(true)
         .stdin(Stdio::piped())
-        .stdout(Stdio::piped());
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let mut process = cmd
+        .spawn()
+        .map_err(|e| EngineError::spawn(engine_path, e))?;
+    let stderr = StderrLog::capture(&mut process);
+
+    if let Err(e) = handshake(&mut process, position, option_values, handshake_timeout).await {
+        return Err(diagnose(e, &mut process, &stderr).await);
+    }
 
-    let mut process = cmd.spawn().expect("Error starting engine");
+    Ok((process, receiver, sender, stderr))
+}
 
+// Ask for uci, set the options and wait until the engine is ready, then give
+// it the start position.
+async fn handshake(
+    process: &mut Child,
+    position: &str,
+    option_values: &[(String, String)],
+    timeout: Duration,
+) -> Result<(), EngineError> {
     let pos = format!("position fen {}\n", position);
-    if let Some(stdin) = process.stdin.as_mut() {
-        stdin.write_all(b"uci\n").await?;
-        stdin.flush().await?;
+    let stdin = process
+        .stdin
+        .as_mut()
+        .ok_or(EngineError::Io("no stdin".into()))?;
+    stdin.write_all(b"uci\n").await?;
+    stdin.flush().await?;
+
+    let stdout = process
+        .stdout
+        .as_mut()
+        .ok_or(EngineError::Io("no stdout".into()))?;
+    let mut buffer_str = String::new();
+    let Some(options) =
+        read_options_from_process(BufReader::new(stdout), &mut buffer_str, timeout).await
+    else {
+        return Err(EngineError::Handshake("no uciok".into()));
+    };
+
+    // Only options the engine reported are set, and only with values that
+    // are valid for them. UCI option names are case insensitive.
+    let stdin = process
+        .stdin
+        .as_mut()
+        .ok_or(EngineError::Io("no stdin".into()))?;
+    for (name, value) in option_values {
+        let command = options
+            .iter()
+            .filter(|option| option.ui_element != UiElement::Button)
+            .find(|option| option.name.eq_ignore_ascii_case(name))
+            .and_then(|option| option.setoption(value));
+
+        match command {
+            Some(command) => stdin.write_all(format!("{}\n", command).as_bytes()).await?,
+            None => eprintln!("Skipping invalid option {} = {}", name, value),
+        }
+    }
+    stdin.write_all(b"isready\n").await?;
+    stdin.flush().await?;
+
+    let stdout = process
+        .stdout
+        .as_mut()
+        .ok_or(EngineError::Io("no stdout".into()))?;
+    if !read_setup_from_process(BufReader::new(stdout), &mut buffer_str, timeout).await {
+        return Err(EngineError::Handshake("no readyok".into()));
+    }
+
+    let stdin = process
+        .stdin
+        .as_mut()
+        .ok_or(EngineError::Io("no stdin".into()))?;
+    stdin.write_all(pos.as_bytes()).await?;
+    stdin.flush().await?;
+
+    Ok(())
+}
+
+// Handle the next message from the UI. When something goes wrong the error
+// tells whether the engine crashed.
+pub async fn handle_engine_thinking(
+    engine: &mut EngineProcess,
+    output: &mut Sender<Message>,
+) -> Result<(), EngineError> {
+    let Some(message) = engine.receiver.recv().await else {
+        return Ok(());
+    };
+
+    match respond(engine, &message, output).await {
+        Ok(()) => Ok(()),
+        Err(e) => Err(diagnose(e, &mut engine.process, &engine.stderr).await),
+    }
+}
+
+async fn respond(
+    engine: &mut EngineProcess,
+    message: &str,
+    output: &mut Sender<Message>,
+) -> Result<(), EngineError> {
+    if message.eq_ignore_ascii_case("stop") || message == "quit" {
+        quit(&mut engine.process).await?;
+        send(output, Message::EngineStopped(true));
+        return Ok(());
+    }
+
+    // Buttons pressed in the options form and the start of a new game
+    // are passed on as they are, after an engine that ponders stopped.
+    if message.starts_with("setoption ") || message == "ucinewgame" {
+        if engine.pondering.take().is_some() {
+            stop_search(engine).await?;
+        }
+        write(&mut engine.process, &format!("{}\n", message)).await?;
+        return Ok(());
+    }
+
+    // A search request is the FEN the info lines are about, followed by
+    // the position and go commands. A bare FEN is searched up to the
+    // engine's depth.
+    let (fen, commands) = match message.split_once('\n') {
+        Some((fen, commands)) => (fen, format!("{}\n", commands.trim())),
+        None => (
+            message,
+            format!(
+                "position fen {}\ngo depth {}\n",
+                message, engine.search_up_to
+            ),
+        ),
+    };
+    let position = commands
+        .lines()
+        .find(|line| line.starts_with("position "))
+        .unwrap_or_default();
+    let go = commands
+        .lines()
+        .find(|line| line.starts_with("go"))
+        .unwrap_or_default();
+
+    // When the opponent played the move the engine pondered on it goes on
+    // searching, otherwise it starts over on the real position.
+    match engine.pondering.take() {
+        Some(pondered) if pondered == position => write(&mut engine.process, "ponderhit\n").await?,
+        Some(_) => {
+            stop_search(engine).await?;
+            write(&mut engine.process, &commands).await?;
+        }
+        None => write(&mut engine.process, &commands).await?,
     }
+    let started = Instant::now();
 
-    let reader = BufReader::new(process.stdout.as_mut().expect("Failed to get stdout"));
+    // The engine gets its search time and the grace period to answer.
+    let white_to_move = fen.split_whitespace().nth(1) != Some("b");
+    let limit = search_time_limit(go, white_to_move)
+        .map(|ms| Duration::from_millis(ms) + engine.move_grace);
+    let response = read_bestmove(engine, Some((fen, output)), limit).await?;
+
+    let last = response.last().map(|line| line.trim()).unwrap_or_default();
+    let bestmove = last
+        .split_whitespace()
+        .nth(1)
+        .ok_or_else(|| EngineError::Malformed(last.to_string()))?;
+
+    // The UI checks the move is legal on its own board, and decides how the
+    // game ends when the engine has no move to play.
+    send(output, Message::EngineMove(bestmove.to_string()));
+
+    // While the opponent thinks the engine searches the move it expects,
+    // with its own clock short of the time it just used.
+    let ponder_move = last
+        .split_whitespace()
+        .skip_while(|&word| word != "ponder")
+        .nth(1);
+    if let (true, Some(ponder_move), false) = (engine.ponder, ponder_move, position.is_empty()) {
+        let pondered = with_moves(position, &[bestmove, ponder_move]);
+        let thought = started.elapsed().as_millis() as u64;
+        let go = ponder_command(go, white_to_move, thought);
+        write(&mut engine.process, &format!("{}\n{}\n", pondered, go)).await?;
+        engine.pondering = Some(pondered);
+    }
+
+    // output
+    //     .try_send(Message::SelectSquare(algebraic_square_to_number(
+    //         &bestmove[0..2],
+    //     )))
+    //     .expect("Error on the mspc channel in the engine subscription");
+    //
+    // output
+    //     .try_send(Message::SelectSquare(algebraic_square_to_number(
+    //         &bestmove[2..4],
+    //     )))
+    //     .expect("Error on the mspc channel in the engine subscription");
+    //
+    // if bestmove.len() >= 5 {
+    //     match &bestmove[4..5] {
+    //         "n" => output
+    //             .try_send(Message::PromotionSelected(PromotionChoice::Knight))
+    //             .expect("Error on the mspc channel in the engine subscription"),
+    //         "q" => output
+    //             .try_send(Message::PromotionSelected(PromotionChoice::Queen))
+    //             .expect("Error on the mspc channel in the engine subscription"),
+    //         "b" => output
+    //             .try_send(Message::PromotionSelected(PromotionChoice::Bishop))
+    //             .expect("Error on the mspc channel in the engine subscription"),
+    //         "r" => output
+    //             .try_send(Message::PromotionSelected(PromotionChoice::Rook))
+    //             .expect("Error on the mspc channel in the engine subscription"),
+    //         "k" => output
+    //             .try_send(Message::LogResult("ok".to_owned()))
+    //             .expect("Error on the mspc channel in the engine subscription"),
+    //         _ => {
+    //             // Handle unexpected characters gracefully
+    //             eprintln!("Unexpected promotion character in bestmove: {}", &bestmove);
+    //             output
+    //                 .try_send(Message::RawMove(response))
+    //                 .expect("Error on the mspc channel in the engine subscription");
+    //         }
+    //     }
+    // }
+
+    Ok(())
+}
+
+// Stop a search and throw away what it found.
+async fn stop_search(engine: &mut EngineProcess) -> Result<(), EngineError> {
+    write(&mut engine.process, "stop\n").await?;
+    let grace = engine.move_grace;
+    read_bestmove(engine, None, Some(grace)).await?;
+
+    Ok(())
+}
+
+// Read up to the engine's bestmove, passing info lines on to the UI when
+// there is a position they are about. An engine that doesn't answer in time
+// is told to stop, and gets the grace period once more.
+pub async fn read_bestmove(
+    engine: &mut EngineProcess,
+    mut info: Option<(&str, &mut Sender<Message>)>,
+    limit: Option<Duration>,
+) -> Result<Vec<String>, EngineError> {
     let mut buffer_str = String::new();
-    if !read_setup_from_process(reader, &mut buffer_str).await {
-        return Err("Failed to initialize engine".into());
+    let stdout = engine
+        .process
+        .stdout
+        .as_mut()
+        .ok_or(EngineError::Io("no stdout".into()))?;
+    let reader = BufReader::new(stdout);
+    let info_to = info.as_mut().map(|(fen, output)| (*fen, &mut **output));
+    match read_moves_from_process(reader, &mut buffer_str, info_to, limit).await {
+        Err(EngineError::Timeout(_)) => (),
+        response => return response,
     }
 
+    write(&mut engine.process, "stop\n").await?;
+    let stdout = engine
+        .process
+        .stdout
+        .as_mut()
+        .ok_or(EngineError::Io("no stdout".into()))?;
+    let info_to = info.as_mut().map(|(fen, output)| (*fen, &mut **output));
+    read_moves_from_process(
+        BufReader::new(stdout),
+        &mut buffer_str,
+        info_to,
+        Some(engine.move_grace),
+    )
+    .await
+    .map_err(|e| match e {
+        EngineError::Timeout(_) => EngineError::Timeout("no bestmove after stop".into()),
+        e => e,
+    })
+}
+
+pub async fn write(process: &mut Child, text: &str) -> Result<(), EngineError> {
+    let stdin = process
+        .stdin
+        .as_mut()
+        .ok_or(EngineError::Io("no stdin".into()))?;
+    stdin.write_all(text.as_bytes()).await?;
+    stdin.flush().await?;
+
+    Ok(())
+}
+
+// Ask the engine to quit, and kill it when it doesn't.
+pub async fn quit(process: &mut Child) -> Result<(), EngineError> {
     if let Some(stdin) = process.stdin.as_mut() {
-        stdin.write_all(b"isready\n").await?;
+        stdin.write_all(b"quit\n").await?;
         stdin.flush().await?;
     }
 
-    let reader = BufReader::new(process.stdout.as_mut().expect("Failed to get stdout"));
-    if !read_setup_from_process(reader, &mut buffer_str).await {
-        return Err("Engine not ready".into());
+    let terminate_timeout = timeout(Duration::from_millis(1000), process.wait()).await;
+    if terminate_timeout.is_err() {
+        let _ = timeout(Duration::from_millis(500), process.kill()).await;
     }
 
-    if let Some(stdin) = process.stdin.as_mut() {
-        stdin.write_all(pos.as_bytes()).await?;
-        stdin.flush().await?;
+    Ok(())
+}
+
+// A full channel means the UI is far behind, and a closed one that it is
+// gone. Neither is the engine's fault.
+fn send(output: &mut Sender<Message>, message: Message) {
+    if let Err(e) = output.try_send(message) {
+        eprintln!("Could not send an engine message to the UI: {}", e);
+    }
+}
+
+// Let the analysis engine search every position it gets until it gets the
+// next one. A search is stopped before the next position is set up, and the
+// info lines the old search sends after the stop are dropped.
+pub async fn run_analysis(
+    engine: &UIengine,
+    output: &mut Sender<Message>,
+) -> Result<(), EngineError> {
+    let (mut process, mut receiver, sender, stderr) = start_engine(
+        &engine.engine_path,
+        &engine.position,
+        &engine.options,
+        engine.handshake_timeout,
+    )
+    .await?;
+    send(output, Message::AnalysisReady(sender));
+
+    if let Err(e) = analyse(&mut process, &mut receiver, output).await {
+        return Err(diagnose(e, &mut process, &stderr).await);
+    }
+
+    if timeout(Duration::from_millis(1000), process.wait())
+        .await
+        .is_err()
+    {
+        let _ = process.kill().await;
     }
 
-    Ok((process, receiver, sender))
+    Ok(())
 }
 
-pub async fn handle_engine_thinking(
+async fn analyse(
     process: &mut Child,
-    search_up_to: u32,
     receiver: &mut tokio::sync::mpsc::Receiver<String>,
     output: &mut Sender<Message>,
-    clock: &Clock,
-) -> Result<(), Box<dyn Error>> {
-    if let Some(message) = receiver.recv().await {
-        if message == "stop" || message == "quit" {
-            if let Some(stdin) = process.stdin.as_mut() {
-                stdin.write_all(b"quit\n").await?;
-                stdin.flush().await?;
-            }
+) -> Result<(), EngineError> {
+    let mut stdin = process
+        .stdin
+        .take()
+        .ok_or(EngineError::Io("no stdin".into()))?;
+    let stdout = process
+        .stdout
+        .take()
+        .ok_or(EngineError::Io("no stdout".into()))?;
+    let mut lines = BufReader::new(stdout).lines();
 
-            let terminate_timeout = timeout(Duration::from_millis(1000), process.wait()).await;
-            if terminate_timeout.is_err() {
-                let _ = timeout(Duration::from_millis(500), process.kill()).await;
-            }
+    // The position being searched, the one to search next and options to
+    // set once the engine is idle.
+    let mut searching: Option<String> = None;
+    let mut next: Option<String> = None;
+    let mut options: Vec<String> = vec![];
+    let mut stopping = false;
 
-            output.try_send(Message::EngineStopped(true))?;
-            return Ok(());
-        }
+    loop {
+        tokio::select! {
+            message = receiver.recv() => {
+                let Some(message) = message else {
+                    break;
+                };
+                if message == "quit" {
+                    break;
+                }
 
-        let pos = format!("position fen {}\n", message);
-        let limit = format!(
-            "go depth {} wtime {} btime {}\n",
-            search_up_to, clock.wtime, clock.btime
-        );
-
-        if let Some(stdin) = process.stdin.as_mut() {
-            stdin.write_all(pos.as_bytes()).await?;
-            stdin.flush().await?;
-            stdin.write_all(limit.as_bytes()).await?;
-            stdin.flush().await?;
-        }
+                if message.starts_with("setoption ") {
+                    // The current position is searched again with the new
+                    // option.
+                    options.push(message);
+                    next = next.or_else(|| searching.clone());
+                } else {
+                    next = Some(message);
+                }
 
-        let reader = BufReader::new(process.stdout.as_mut().expect("Failed to get stdout"));
-        let mut buffer_str = String::new();
-        let response = read_moves_from_process(reader, &mut buffer_str).await?;
-
-        // output
-        //     .try_send(Message::RawMove(response.clone()))
-        //     .expect("Error on the mspc channel in the engine subscription");
-        let bestmove = response.last().unwrap().split_whitespace().nth(1).unwrap();
-
-        let mate_or_draw = response[response.len() - 2]
-            .split_whitespace()
-            .nth(2)
-            .unwrap_or("Helaas");
-
-        let mate_in = response[response.len() - 2]
-            .split_whitespace()
-            .nth(3)
-            .unwrap_or("Helaas")
-            .parse::<i8>();
-
-        if mate_or_draw == "draw" {
-            output
-                .try_send(Message::EngineMove(
-                    None,
-                    None,
-                    None,
-                    Some(mate_or_draw.to_string()),
-                ))
-                .expect("Error on the mspc channel in the engine subscription");
-            return Ok(());
-        }
+                if searching.is_some() && !stopping {
+                    stdin.write_all(b"stop\n").await?;
+                    stdin.flush().await?;
+                    stopping = true;
+                }
+            }
+            line = lines.next_line() => {
+                let Some(line) = line? else {
+                    return Err(EngineError::Io("analysis engine closed its output".into()));
+                };
 
-        if mate_or_draw == "mate" && mate_in.unwrap_or(1) == 0 {
-            output
-                .try_send(Message::EngineMove(
-                    None,
-                    None,
-                    None,
-                    Some(mate_or_draw.to_string()),
-                ))
-                .expect("Error on the mspc channel in the engine subscription");
-            return Ok(());
+                if line.starts_with("bestmove") {
+                    searching = None;
+                    stopping = false;
+                } else if let (Some(request), Some(info), false) =
+                    (&searching, EngineInfo::parse(&line), stopping)
+                {
+                    let fen = request.lines().next().unwrap_or_default();
+                    let _ = output.try_send(Message::EngineInfo(fen.to_string(), info));
+                }
+            }
         }
 
-        let (p1, p2) = (
-            algebraic_square_to_number(&bestmove[0..2]),
-            algebraic_square_to_number(&bestmove[2..4]),
-        );
-
-        if bestmove.len() >= 5 {
-            match &bestmove[4..5] {
-                "q" => output
-                    .try_send(Message::EngineMove(p1, p2, Some(1), None))
-                    .expect("Error on the mspc channel in the engine subscription"),
-                "r" => output
-                    .try_send(Message::EngineMove(p1, p2, Some(2), None))
-                    .expect("Error on the mspc channel in the engine subscription"),
-                "b" => output
-                    .try_send(Message::EngineMove(p1, p2, Some(3), None))
-                    .expect("Error on the mspc channel in the engine subscription"),
-                "n" => output
-                    .try_send(Message::EngineMove(p1, p2, Some(4), None))
-                    .expect("Error on the mspc channel in the engine subscription"),
-                _ => {
-                    // Handle unexpected characters gracefully
-                    eprintln!("Unexpected promotion character in bestmove: {}", &bestmove);
-                    output
-                        .try_send(Message::RawMove(response))
-                        .expect("Error on the mspc channel in the engine subscription");
+        if searching.is_none() {
+            if let Some(request) = next.take() {
+                for option in options.drain(..) {
+                    stdin.write_all(format!("{}\n", option).as_bytes()).await?;
                 }
+                // A request is the FEN the info lines are tagged with,
+                // followed by the position with the moves of the game. A bare
+                // FEN is searched on its own.
+                let position = match request.split_once('\n') {
+                    Some((_, position)) => position.trim().to_string(),
+                    None => format!("position fen {}", request),
+                };
+                let go = format!("{}\ngo infinite\n", position);
+                stdin.write_all(go.as_bytes()).await?;
+                stdin.flush().await?;
+                searching = Some(request);
             }
-        } else {
-            output
-                .try_send(Message::EngineMove(p1, p2, None, None))
-                .expect("Error on the mspc channel in the engine subscription");
         }
-
-        // output
-        //     .try_send(Message::SelectSquare(algebraic_square_to_number(
-        //         &bestmove[0..2],
-        //     )))
-        //     .expect("Error on the mspc channel in the engine subscription");
-        //
-        // output
-        //     .try_send(Message::SelectSquare(algebraic_square_to_number(
-        //         &bestmove[2..4],
-        //     )))
-        //     .expect("Error on the mspc channel in the engine subscription");
-        //
-        // if bestmove.len() >= 5 {
-        //     match &bestmove[4..5] {
-        //         "n" => output
-        //             .try_send(Message::PromotionSelected(PromotionChoice::Knight))
-        //             .expect("Error on the mspc channel in the engine subscription"),
-        //         "q" => output
-        //             .try_send(Message::PromotionSelected(PromotionChoice::Queen))
-        //             .expect("Error on the mspc channel in the engine subscription"),
-        //         "b" => output
-        //             .try_send(Message::PromotionSelected(PromotionChoice::Bishop))
-        //             .expect("Error on the mspc channel in the engine subscription"),
-        //         "r" => output
-        //             .try_send(Message::PromotionSelected(PromotionChoice::Rook))
-        //             .expect("Error on the mspc channel in the engine subscription"),
-        //         "k" => output
-        //             .try_send(Message::LogResult("ok".to_owned()))
-        //             .expect("Error on the mspc channel in the engine subscription"),
-        //         _ => {
-        //             // Handle unexpected characters gracefully
-        //             eprintln!("Unexpected promotion character in bestmove: {}", &bestmove);
-        //             output
-        //                 .try_send(Message::RawMove(response))
-        //                 .expect("Error on the mspc channel in the engine subscription");
-        //         }
-        //     }
-        // }
     }
 
+    let _ = stdin.write_all(b"stop\nquit\n").await;
+    let _ = stdin.flush().await;
+
     Ok(())
 }
@@ -0,0 +1,167 @@
+use iced::mouse;
+use iced::widget::canvas::{self, event, Canvas, Frame, Geometry, Path, Stroke};
+use iced::{Color, Element, Length, Point, Rectangle, Size, Theme};
+
+use super::game::{GameTree, NodeId};
+use super::ui::Message;
+
+const GRAPH_HEIGHT: f32 = 120.0;
+
+struct GraphPoint {
+    node: NodeId,
+    // White's chance of winning in percent.
+    eval: f32,
+    blunder: bool,
+}
+
+// White's chance of winning over the line through the current position.
+// Positions without a score keep the value of the one before them.
+pub struct EvalGraph {
+    points: Vec<GraphPoint>,
+    current: usize,
+}
+
+impl EvalGraph {
+    pub fn new(game: &GameTree) -> Self {
+        let mut points = vec![];
+        let mut eval = 50.0;
+
+        for node in std::iter::once(0).chain(game.line()) {
+            eval = game.white_win_percent(node).unwrap_or(eval);
+            points.push(GraphPoint {
+                node,
+                eval,
+                blunder: game.is_blunder(node),
+            });
+        }
+
+        let current = points
+            .iter()
+            .position(|point| point.node == game.current)
+            .unwrap_or(0);
+
+        Self { points, current }
+    }
+
+    fn x(&self, index: usize, width: f32) -> f32 {
+        let steps = (self.points.len() - 1).max(1) as f32;
+        index as f32 * width / steps
+    }
+
+    fn point(&self, index: usize, size: Size) -> Point {
+        let y = size.height * (1.0 - self.points[index].eval / 100.0);
+        Point::new(self.x(index, size.width), y)
+    }
+
+    // The position nearest to the cursor, if it is on the graph.
+    fn index_at(&self, bounds: Rectangle, cursor: mouse::Cursor) -> Option<usize> {
+        let position = cursor.position_in(bounds)?;
+        let steps = (self.points.len() - 1).max(1) as f32;
+        let index = (position.x / bounds.width * steps).round() as usize;
+
+        Some(index.min(self.points.len() - 1))
+    }
+}
+
+impl canvas::Program<Message, iced::Renderer<Theme>> for EvalGraph {
+    type State = ();
+
+    fn update(
+        &self,
+        _state: &mut Self::State,
+        event: canvas::Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> (event::Status, Option<Message>) {
+        if let canvas::Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) = event {
+            if let Some(index) = self.index_at(bounds, cursor) {
+                return (
+                    event::Status::Captured,
+                    Some(Message::GoToMove(self.points[index].node)),
+                );
+            }
+        }
+
+        (event::Status::Ignored, None)
+    }
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &iced::Renderer<Theme>,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let size = bounds.size();
+        let mut frame = Frame::new(renderer, size);
+
+        frame.fill_rectangle(Point::ORIGIN, size, Color::from_rgb(0.2, 0.2, 0.2));
+
+        // White's share is filled from the bottom up to the graph line.
+        let area = Path::new(|builder| {
+            builder.move_to(Point::new(0.0, size.height));
+            for index in 0..self.points.len() {
+                builder.line_to(self.point(index, size));
+            }
+            builder.line_to(Point::new(
+                self.x(self.points.len() - 1, size.width),
+                size.height,
+            ));
+            builder.close();
+        });
+        frame.fill(&area, Color::from_rgb(0.85, 0.85, 0.85));
+
+        let middle = Path::line(
+            Point::new(0.0, size.height / 2.0),
+            Point::new(size.width, size.height / 2.0),
+        );
+        frame.stroke(
+            &middle,
+            Stroke::default()
+                .with_width(1.0)
+                .with_color(Color::from_rgba(0.5, 0.5, 0.5, 0.8)),
+        );
+
+        let current_x = self.x(self.current, size.width);
+        let current = Path::line(
+            Point::new(current_x, 0.0),
+            Point::new(current_x, size.height),
+        );
+        frame.stroke(
+            &current,
+            Stroke::default()
+                .with_width(2.0)
+                .with_color(Color::from_rgb(0.2, 0.5, 0.9)),
+        );
+
+        for (index, point) in self.points.iter().enumerate() {
+            if point.blunder {
+                let dot = Path::circle(self.point(index, size), 4.0);
+                frame.fill(&dot, Color::from_rgb(0.85, 0.15, 0.15));
+            }
+        }
+
+        vec![frame.into_geometry()]
+    }
+
+    fn mouse_interaction(
+        &self,
+        _state: &Self::State,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> mouse::Interaction {
+        if cursor.is_over(bounds) {
+            mouse::Interaction::Pointer
+        } else {
+            mouse::Interaction::default()
+        }
+    }
+}
+
+pub fn eval_graph<'a>(game: &GameTree) -> Element<'a, Message, iced::Renderer<Theme>> {
+    Canvas::new(EvalGraph::new(game))
+        .width(Length::Fill)
+        .height(GRAPH_HEIGHT)
+        .into()
+}
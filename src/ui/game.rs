@@ -0,0 +1,494 @@
+use super::notation::move_to_uci;
+use super::uci::Score;
+use crate::board::defs::Pieces;
+use crate::board::Board;
+use crate::defs::{Side, Sides, Square};
+use crate::movegen::defs::Move;
+use crate::movegen::MoveGenerator;
+use iced::Color;
+
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+pub type NodeId = usize;
+
+// Percentage points of winning chance a move has to throw away to count as a
+// blunder.
+pub const BLUNDER_THRESHOLD: f32 = 15.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Navigation {
+    Start,
+    Back,
+    Forward,
+    End,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnnotationColor {
+    Green,
+    Red,
+    Yellow,
+    Blue,
+}
+
+impl AnnotationColor {
+    // Letter used for the color in [%cal] and [%csl] PGN commands.
+    pub fn letter(&self) -> char {
+        match self {
+            AnnotationColor::Green => 'G',
+            AnnotationColor::Red => 'R',
+            AnnotationColor::Yellow => 'Y',
+            AnnotationColor::Blue => 'B',
+        }
+    }
+
+    pub fn from_letter(letter: char) -> Option<Self> {
+        match letter {
+            'G' => Some(AnnotationColor::Green),
+            'R' => Some(AnnotationColor::Red),
+            'Y' => Some(AnnotationColor::Yellow),
+            'B' => Some(AnnotationColor::Blue),
+            _ => None,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            AnnotationColor::Green => Color::from_rgba(0.08, 0.47, 0.3, 0.8),
+            AnnotationColor::Red => Color::from_rgba(0.8, 0.16, 0.16, 0.8),
+            AnnotationColor::Yellow => Color::from_rgba(0.9, 0.68, 0.0, 0.8),
+            AnnotationColor::Blue => Color::from_rgba(0.0, 0.38, 0.75, 0.8),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Arrow {
+    pub from: Square,
+    pub to: Square,
+    pub color: AnnotationColor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SquareMark {
+    pub square: Square,
+    pub color: AnnotationColor,
+}
+
+// Arrows and circled squares drawn by the user on a position.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Annotations {
+    pub arrows: Vec<Arrow>,
+    pub squares: Vec<SquareMark>,
+}
+
+impl Annotations {
+    pub fn is_empty(&self) -> bool {
+        self.arrows.is_empty() && self.squares.is_empty()
+    }
+
+    // Drawing the same arrow again removes it, drawing it in another color
+    // recolors it.
+    pub fn toggle_arrow(&mut self, from: Square, to: Square, color: AnnotationColor) {
+        match self
+            .arrows
+            .iter()
+            .position(|a| a.from == from && a.to == to)
+        {
+            Some(i) if self.arrows[i].color == color => {
+                self.arrows.remove(i);
+            }
+            Some(i) => self.arrows[i].color = color,
+            None => self.arrows.push(Arrow { from, to, color }),
+        }
+    }
+
+    pub fn toggle_square(&mut self, square: Square, color: AnnotationColor) {
+        match self.squares.iter().position(|s| s.square == square) {
+            Some(i) if self.squares[i].color == color => {
+                self.squares.remove(i);
+            }
+            Some(i) => self.squares[i].color = color,
+            None => self.squares.push(SquareMark { square, color }),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.arrows.clear();
+        self.squares.clear();
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GameNode {
+    pub mv: Option<Move>,
+    pub san: String,
+    pub parent: Option<NodeId>,
+    // The first child is the main line, the others are variations.
+    pub children: Vec<NodeId>,
+    pub annotations: Annotations,
+    pub comment: String,
+    // The engine's score of the position, from the side to move's view.
+    pub score: Option<Score>,
+}
+
+// All moves played or analysed from a start position, including variations.
+// Node 0 is the start position itself.
+#[derive(Debug, Clone)]
+pub struct GameTree {
+    pub start_fen: String,
+    pub headers: Vec<(String, String)>,
+    pub nodes: Vec<GameNode>,
+    pub current: NodeId,
+}
+
+impl Default for GameTree {
+    fn default() -> Self {
+        GameTree::new(START_FEN)
+    }
+}
+
+impl GameTree {
+    pub fn new(start_fen: &str) -> Self {
+        Self {
+            start_fen: start_fen.to_string(),
+            headers: vec![],
+            nodes: vec![GameNode::default()],
+            current: 0,
+        }
+    }
+
+    pub fn current_node(&self) -> &GameNode {
+        &self.nodes[self.current]
+    }
+
+    pub fn current_node_mut(&mut self) -> &mut GameNode {
+        &mut self.nodes[self.current]
+    }
+
+    // Play a move from the current node. If the move was already played
+    // from here that line is followed, otherwise a new variation is added.
+    pub fn play(&mut self, mv: Move, san: String) -> NodeId {
+        let existing = self.nodes[self.current]
+            .children
+            .iter()
+            .copied()
+            .find(|&child| self.nodes[child].mv.map(|m| m.get_move()) == Some(mv.get_move()));
+
+        if let Some(child) = existing {
+            self.current = child;
+            return child;
+        }
+
+        let id = self.nodes.len();
+        self.nodes.push(GameNode {
+            mv: Some(mv),
+            san,
+            parent: Some(self.current),
+            ..GameNode::default()
+        });
+        self.nodes[self.current].children.push(id);
+        self.current = id;
+
+        id
+    }
+
+    pub fn back(&mut self) -> bool {
+        match self.nodes[self.current].parent {
+            Some(parent) => {
+                self.current = parent;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn forward(&mut self) -> bool {
+        match self.nodes[self.current].children.first() {
+            Some(&child) => {
+                self.current = child;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn to_start(&mut self) {
+        self.current = 0;
+    }
+
+    pub fn to_end(&mut self) {
+        while self.forward() {}
+    }
+
+    pub fn navigate(&mut self, navigation: Navigation) {
+        match navigation {
+            Navigation::Start => self.to_start(),
+            Navigation::Back => {
+                self.back();
+            }
+            Navigation::Forward => {
+                self.forward();
+            }
+            Navigation::End => self.to_end(),
+        }
+    }
+
+    pub fn go_to(&mut self, node: NodeId) {
+        if node < self.nodes.len() {
+            self.current = node;
+        }
+    }
+
+    // Nodes from the first move up to and including the given node.
+    pub fn path(&self, node: NodeId) -> Vec<NodeId> {
+        let mut path = vec![];
+        let mut id = node;
+        while let Some(parent) = self.nodes[id].parent {
+            path.push(id);
+            id = parent;
+        }
+        path.reverse();
+
+        path
+    }
+
+    // The line through the current node: the moves leading to it followed by
+    // the main continuation after it.
+    pub fn line(&self) -> Vec<NodeId> {
+        let mut line = self.path(self.current);
+        let mut id = self.current;
+        while let Some(&child) = self.nodes[id].children.first() {
+            line.push(child);
+            id = child;
+        }
+
+        line
+    }
+
+    // Ply count of the start position, taken from the move number and side
+    // to move of its FEN.
+    pub fn first_ply(&self) -> usize {
+        let mut fields = self.start_fen.split_whitespace().skip(1);
+        let black = fields.next() == Some("b");
+        let move_number = fields
+            .nth(3)
+            .and_then(|n| n.parse::<usize>().ok())
+            .unwrap_or(1);
+
+        (move_number.max(1) - 1) * 2 + black as usize
+    }
+
+    // The position at the given node, with the moves leading to it in the
+    // board history.
+    pub fn board_at(&self, node: NodeId, movegen: &MoveGenerator) -> Board {
+        let mut board = Board::new();
+        let _ = board.read_fen(Some(&self.start_fen));
+
+        for id in self.path(node) {
+            if let Some(mv) = self.nodes[id].mv {
+                board.make_move(mv, movegen);
+            }
+        }
+
+        board
+    }
+
+    // The UCI position command for the node: the start position with the
+    // moves leading to it, so engines see the game's history.
+    pub fn uci_position(&self, node: NodeId) -> String {
+        let mut position = if self.start_fen == START_FEN {
+            "position startpos".to_string()
+        } else {
+            format!("position fen {}", self.start_fen)
+        };
+
+        let moves: Vec<String> = self
+            .path(node)
+            .into_iter()
+            .filter_map(|id| self.nodes[id].mv)
+            .map(move_to_uci)
+            .collect();
+        if !moves.is_empty() {
+            position.push_str(" moves ");
+            position.push_str(&moves.join(" "));
+        }
+
+        position
+    }
+
+    pub fn white_to_move(&self, node: NodeId) -> bool {
+        (self.first_ply() + self.path(node).len()).is_multiple_of(2)
+    }
+
+    // White's chance of winning at the node in percent, if it has a score.
+    pub fn white_win_percent(&self, node: NodeId) -> Option<f32> {
+        let score = self.nodes[node].score?;
+        Some(score.white_win_percent(self.white_to_move(node)))
+    }
+
+    // Whether the move leading to the node threw away the game, going by the
+    // scores before and after it.
+    pub fn is_blunder(&self, node: NodeId) -> bool {
+        let Some(parent) = self.nodes[node].parent else {
+            return false;
+        };
+        let (Some(before), Some(after)) =
+            (self.white_win_percent(parent), self.white_win_percent(node))
+        else {
+            return false;
+        };
+
+        let loss = if self.white_to_move(parent) {
+            before - after
+        } else {
+            after - before
+        };
+        loss >= BLUNDER_THRESHOLD
+    }
+
+    pub fn header(&self, key: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn set_header(&mut self, key: &str, value: &str) {
+        match self.headers.iter_mut().find(|(k, _)| k == key) {
+            Some(header) => header.1 = value.to_string(),
+            None => self.headers.push((key.to_string(), value.to_string())),
+        }
+    }
+}
+
+// Whether the side could still mate with some series of legal moves, which
+// decides if running out of time loses or draws. A lone king never mates,
+// and a king with a single minor piece only when the other side has material
+// to block its own king with.
+pub fn can_mate(board: &Board, side: Side) -> bool {
+    let count = |side: Side, piece| board.get_pieces(piece, side).count_ones();
+    let minors = count(side, Pieces::BISHOP) + count(side, Pieces::KNIGHT);
+    let majors_or_pawns =
+        count(side, Pieces::QUEEN) + count(side, Pieces::ROOK) + count(side, Pieces::PAWN);
+
+    let opponent = side ^ 1;
+    let opponent_material = [
+        Pieces::QUEEN,
+        Pieces::ROOK,
+        Pieces::BISHOP,
+        Pieces::KNIGHT,
+        Pieces::PAWN,
+    ]
+    .iter()
+    .any(|&piece| count(opponent, piece) > 0);
+
+    majors_or_pawns > 0 || minors > 1 || (minors == 1 && opponent_material)
+}
+
+// Why the position is drawn by the rules, if it is: fifty moves without a
+// capture or pawn move, the third time the position is on the board, or no
+// mate left for either side.
+pub fn draw_by_rule(board: &Board) -> Option<&'static str> {
+    let halfmoves = board.gamestate.halfclock_move as usize;
+    if halfmoves >= 100 {
+        return Some("fifty move rule");
+    }
+
+    // A position can only repeat since the last capture or pawn move.
+    let key = board.gamestate.zobrist_key;
+    let played = board.history.len();
+    let repetitions = (played.saturating_sub(halfmoves)..played)
+        .filter(|&ply| board.history.get_ref(ply).zobrist_key == key)
+        .count();
+    if repetitions >= 2 {
+        return Some("threefold repetition");
+    }
+
+    if !can_mate(board, Sides::WHITE) && !can_mate(board, Sides::BLACK) {
+        return Some("insufficient material");
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::notation::uci_to_move;
+    use super::*;
+
+    fn board(fen: &str) -> Board {
+        let mut board = Board::new();
+        board.read_fen(Some(fen)).unwrap();
+        board
+    }
+
+    #[test]
+    fn material_to_mate_with() {
+        // A knight can't mate a lone king.
+        let knight = board("8/8/4k3/8/8/8/3N4/4K3 w - - 0 1");
+        assert!(!can_mate(&knight, Sides::WHITE));
+        assert!(!can_mate(&knight, Sides::BLACK));
+        assert_eq!(draw_by_rule(&knight), Some("insufficient material"));
+
+        // But it can when the other side has a piece to block its king with.
+        let minors = board("8/8/4k3/3n4/8/8/3B4/4K3 w - - 0 1");
+        assert!(can_mate(&minors, Sides::WHITE));
+        assert!(can_mate(&minors, Sides::BLACK));
+        assert_eq!(draw_by_rule(&minors), None);
+
+        // A lone king that flags loses to a rook, but draws against a knight.
+        let rook = board("8/8/4k3/8/8/8/3R4/4K3 b - - 0 1");
+        assert!(!can_mate(&rook, Sides::BLACK));
+        assert!(can_mate(&rook, Sides::WHITE));
+        assert!(!can_mate(&knight, Sides::WHITE));
+    }
+
+    #[test]
+    fn draws_by_rule() {
+        let movegen = MoveGenerator::new();
+        let mut fifty = board("8/8/4k3/8/8/8/3R4/4K3 w - - 99 80");
+        assert_eq!(draw_by_rule(&fifty), None);
+        let m = uci_to_move(&fifty, &movegen, "d2a2").unwrap();
+        assert!(fifty.make_move(m, &movegen));
+        assert_eq!(draw_by_rule(&fifty), Some("fifty move rule"));
+        let mut board = board(START_FEN);
+        for (ply, uci) in ["g1f3", "g8f6", "f3g1", "f6g8"].iter().cycle().enumerate() {
+            if ply == 8 {
+                break;
+            }
+            assert_eq!(draw_by_rule(&board), None);
+            let m = uci_to_move(&board, &movegen, uci).unwrap();
+            assert!(board.make_move(m, &movegen));
+        }
+        assert_eq!(draw_by_rule(&board), Some("threefold repetition"));
+    }
+
+    #[test]
+    fn engines_get_the_moves_of_the_game() {
+        let movegen = MoveGenerator::new();
+        let play = |start_fen: &str, moves: &[&str]| {
+            let mut game = GameTree::new(start_fen);
+            for uci in moves {
+                let board = game.board_at(game.current, &movegen);
+                let m = uci_to_move(&board, &movegen, uci).unwrap();
+                game.play(m, String::new());
+            }
+            game
+        };
+
+        let moves = ["e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "f8c5", "e1g1"];
+        let game = play(START_FEN, &moves);
+        assert_eq!(
+            game.uci_position(game.current),
+            "position startpos moves e2e4 e7e5 g1f3 b8c6 f1c4 f8c5 e1g1"
+        );
+        assert_eq!(game.uci_position(0), "position startpos");
+
+        let game = play("8/P6k/8/8/8/8/8/K7 w - - 0 1", &["a7a8n"]);
+        assert_eq!(
+            game.uci_position(game.current),
+            "position fen 8/P6k/8/8/8/8/8/K7 w - - 0 1 moves a7a8n"
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use crate::board::defs::{Pieces, PIECE_CHAR_CAPS, SQUARE_NAME};
+use crate::board::Board;
+use crate::defs::Piece;
+use crate::extra::parse::algebraic_square_to_number;
+use crate::movegen::defs::Move;
+use crate::movegen::MoveGenerator;
+
+const FILE_NAMES: [char; 8] = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
+
+// Standard algebraic notation of a legal move in the given position,
+// including the check or mate suffix.
+pub fn move_to_san(board: &Board, movegen: &MoveGenerator, m: Move) -> String {
+    let legal_moves = board.legal_moves(movegen);
+    let mut san = san_without_suffix(m, &legal_moves);
+
+    let mut after = board.clone();
+    if after.make_move(m, movegen) {
+        let side = after.side_to_move();
+        let king = after.king_square(side);
+        if movegen.square_attacked(&after, side ^ 1, king) {
+            if after.legal_moves(movegen).is_empty() {
+                san.push('#');
+            } else {
+                san.push('+');
+            }
+        }
+    }
+
+    san
+}
+
+fn san_without_suffix(m: Move, legal_moves: &[Move]) -> String {
+    if m.castling() {
+        return if m.to() % 8 == 6 { "O-O" } else { "O-O-O" }.to_string();
+    }
+
+    let capture = m.captured() != Pieces::NONE || m.en_passant();
+    let mut san = String::new();
+
+    if m.piece() == Pieces::PAWN {
+        if capture {
+            san.push(FILE_NAMES[m.from() % 8]);
+        }
+    } else {
+        san.push_str(PIECE_CHAR_CAPS[m.piece()]);
+
+        // Other pieces of the same kind that can reach the same square.
+        let rivals: Vec<&Move> = legal_moves
+            .iter()
+            .filter(|x| x.piece() == m.piece() && x.to() == m.to() && x.from() != m.from())
+            .collect();
+
+        if !rivals.is_empty() {
+            let same_file = rivals.iter().any(|x| x.from() % 8 == m.from() % 8);
+            let same_rank = rivals.iter().any(|x| x.from() / 8 == m.from() / 8);
+
+            if !same_file {
+                san.push(FILE_NAMES[m.from() % 8]);
+            } else if !same_rank {
+                san.push_str(&(m.from() / 8 + 1).to_string());
+            } else {
+                san.push_str(SQUARE_NAME[m.from()]);
+            }
+        }
+    }
+
+    if capture {
+        san.push('x');
+    }
+    san.push_str(SQUARE_NAME[m.to()]);
+
+    if m.promoted() != Pieces::NONE {
+        san.push('=');
+        san.push_str(PIECE_CHAR_CAPS[m.promoted()]);
+    }
+
+    san
+}
+
+// Find the legal move written in standard algebraic notation. Check, mate
+// and annotation suffixes are ignored.
+pub fn san_to_move(board: &Board, movegen: &MoveGenerator, san: &str) -> Option<Move> {
+    let san = san.trim_end_matches(['+', '#', '!', '?']);
+    let legal_moves = board.legal_moves(movegen);
+
+    let castle = san.replace('0', "O");
+    if castle == "O-O" || castle == "O-O-O" {
+        let file = if castle == "O-O" { 6 } else { 2 };
+        return legal_moves
+            .into_iter()
+            .find(|m| m.castling() && m.to() % 8 == file);
+    }
+
+    let mut chars: Vec<char> = san
+        .chars()
+        .filter(|c| !matches!(c, 'x' | '-' | '='))
+        .collect();
+    let piece = match chars.first() {
+        Some(c) if c.is_ascii_uppercase() => piece_from_char(chars.remove(0))?,
+        _ => Pieces::PAWN,
+    };
+
+    // Promotions are written as "e8=Q", but "e8Q" is common as well.
+    let promoted = match chars.last() {
+        Some(c) if c.is_ascii_uppercase() => Some(piece_from_char(chars.pop()?)?),
+        _ => None,
+    };
+
+    if chars.len() < 2 {
+        return None;
+    }
+    let target: String = chars.split_off(chars.len() - 2).into_iter().collect();
+    let to = algebraic_square_to_number(&target)?;
+
+    // Whatever is left disambiguates the origin square.
+    let from_file = chars.iter().find(|c| c.is_ascii_lowercase());
+    let from_rank = chars.iter().find(|c| c.is_ascii_digit());
+
+    legal_moves.into_iter().find(|m| {
+        m.piece() == piece
+            && m.to() == to
+            && !m.castling()
+            && from_file.is_none_or(|f| FILE_NAMES[m.from() % 8] == *f)
+            && from_rank.is_none_or(|r| (m.from() / 8 + 1).to_string() == r.to_string())
+            && match promoted {
+                Some(p) => m.promoted() == p,
+                None => m.promoted() == Pieces::NONE || m.promoted() == Pieces::QUEEN,
+            }
+    })
+}
+
+// A move in the long algebraic notation engines use. Castling is written as
+// the king's move, like "e1g1".
+pub fn move_to_uci(m: Move) -> String {
+    let mut uci = format!("{}{}", SQUARE_NAME[m.from()], SQUARE_NAME[m.to()]);
+    if m.promoted() != Pieces::NONE {
+        uci.push_str(&PIECE_CHAR_CAPS[m.promoted()].to_ascii_lowercase());
+    }
+
+    uci
+}
+
+// Find the legal move written in the long algebraic notation engines use,
+// like "e2e4" or "e7e8q". A promotion needs its piece. Castling can also be
+// written as the king taking its own rook, like "e1h1".
+pub fn uci_to_move(board: &Board, movegen: &MoveGenerator, uci: &str) -> Option<Move> {
+    if uci.len() < 4 || !uci.is_ascii() {
+        return None;
+    }
+
+    let from = algebraic_square_to_number(&uci[0..2])?;
+    let to = algebraic_square_to_number(&uci[2..4])?;
+    let promoted = match uci[4..].chars().next() {
+        Some(c) => piece_from_char(c.to_ascii_uppercase())?,
+        None => Pieces::NONE,
+    };
+
+    let legal = board.legal_moves(movegen);
+    let found = legal
+        .iter()
+        .find(|m| m.from() == from && m.to() == to && m.promoted() == promoted);
+
+    // The rook is three squares from the king on the king side and four on
+    // the queen side.
+    let castling = || {
+        legal.iter().find(|m| {
+            m.castling()
+                && m.from() == from
+                && promoted == Pieces::NONE
+                && if m.to() > from {
+                    to == from + 3
+                } else {
+                    to + 4 == from
+                }
+        })
+    };
+
+    found.or_else(castling).copied()
+}
+
+// The moves of an engine line in standard algebraic notation. The line stops
+// at the first move that isn't legal.
+pub fn uci_line_to_san(board: &Board, movegen: &MoveGenerator, line: &[String]) -> Vec<String> {
+    let mut board = board.clone();
+    let mut sans = vec![];
+
+    for uci in line {
+        let Some(m) = uci_to_move(&board, movegen, uci) else {
+            break;
+        };
+        sans.push(move_to_san(&board, movegen, m));
+        board.make_move(m, movegen);
+    }
+
+    sans
+}
+
+fn piece_from_char(c: char) -> Option<Piece> {
+    match c {
+        'K' => Some(Pieces::KING),
+        'Q' => Some(Pieces::QUEEN),
+        'R' => Some(Pieces::ROOK),
+        'B' => Some(Pieces::BISHOP),
+        'N' => Some(Pieces::KNIGHT),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(fen: &str) -> Board {
+        let mut board = Board::new();
+        board.read_fen(Some(fen)).unwrap();
+        board
+    }
+
+    #[test]
+    fn engine_moves_are_legal_moves() {
+        let movegen = MoveGenerator::new();
+
+        let promotion = board("8/4P3/8/8/8/8/k7/4K3 w - - 0 1");
+        let knight = uci_to_move(&promotion, &movegen, "e7e8n").unwrap();
+        assert_eq!(knight.promoted(), Pieces::KNIGHT);
+        assert_eq!(uci_to_move(&promotion, &movegen, "e7e8"), None);
+        assert_eq!(uci_to_move(&promotion, &movegen, "e7e8k"), None);
+
+        let castling = board("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        for uci in ["e1g1", "e1h1"] {
+            let m = uci_to_move(&castling, &movegen, uci).unwrap();
+            assert!(m.castling());
+            assert_eq!(move_to_uci(m), "e1g1");
+        }
+        assert_eq!(
+            move_to_uci(uci_to_move(&castling, &movegen, "e1a1").unwrap()),
+            "e1c1"
+        );
+
+        // A piece that isn't there, or a king left in check.
+        assert_eq!(uci_to_move(&castling, &movegen, "d1d2"), None);
+        let check = board("4k3/8/8/8/8/8/4r3/4K3 w - - 0 1");
+        assert_eq!(uci_to_move(&check, &movegen, "e1f2"), None);
+        assert!(uci_to_move(&check, &movegen, "e1e2").is_some());
+    }
+}
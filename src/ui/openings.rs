@@ -0,0 +1,259 @@
+use std::fs;
+use std::path::Path;
+
+use rand::seq::SliceRandom;
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaChaRng;
+use serde::{Deserialize, Serialize};
+
+use super::game::GameTree;
+use super::notation::{move_to_san, move_to_uci, uci_to_move};
+use super::pgn;
+use crate::board::Board;
+use crate::movegen::MoveGenerator;
+
+// A position tournament games start from: a FEN and the moves played from
+// it, so the engines get the moves too.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Opening {
+    pub name: String,
+    pub fen: String,
+    pub moves: Vec<String>,
+}
+
+// The order openings are taken from a suite in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum OpeningOrder {
+    #[default]
+    Sequential,
+    // Shuffled with a seed, so the same seed gives the same openings.
+    Random,
+}
+
+impl OpeningOrder {
+    pub const ALL: [OpeningOrder; 2] = [OpeningOrder::Sequential, OpeningOrder::Random];
+}
+
+impl std::fmt::Display for OpeningOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                OpeningOrder::Sequential => "Sequential",
+                OpeningOrder::Random => "Random",
+            }
+        )
+    }
+}
+
+// Read an opening suite. An .epd file has a position on every line, from any
+// other file the first plies of every game are taken as PGN.
+pub fn load(path: &Path, plies: usize) -> Result<Vec<Opening>, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+
+    let is_epd = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("epd"));
+    let openings = if is_epd {
+        parse_epd(&text)?
+    } else {
+        parse_pgn(&text, plies, &MoveGenerator::new())?
+    };
+
+    if openings.is_empty() {
+        return Err(format!("No openings in {}", path.display()));
+    }
+    Ok(openings)
+}
+
+// Every line is a position: the first four FEN fields followed by opcodes.
+// The id opcode names the opening.
+pub fn parse_epd(text: &str) -> Result<Vec<Opening>, String> {
+    let mut openings = vec![];
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 4 {
+            return Err(format!(
+                "Line {} is not an EPD position: {}",
+                number + 1,
+                line
+            ));
+        }
+        // Some suites have full FENs, with the move counters.
+        let counters = fields.len() >= 6 && fields[4..6].iter().all(|f| f.parse::<u32>().is_ok());
+        let (fen, operations) = if counters {
+            (fields[..6].join(" "), fields[6..].join(" "))
+        } else {
+            (
+                format!("{} 0 1", fields[..4].join(" ")),
+                fields[4..].join(" "),
+            )
+        };
+        if Board::new().read_fen(Some(&fen)).is_err() {
+            return Err(format!(
+                "Line {} has an invalid position: {}",
+                number + 1,
+                line
+            ));
+        }
+
+        let name = operations
+            .split(';')
+            .find_map(|operation| operation.trim().strip_prefix("id "))
+            .map(|id| id.trim().trim_matches('"').to_string())
+            .unwrap_or_else(|| format!("Position {}", number + 1));
+        openings.push(Opening {
+            name,
+            fen,
+            moves: vec![],
+        });
+    }
+
+    Ok(openings)
+}
+
+// The first plies of the main line of every game, from the position the
+// game starts from.
+pub fn parse_pgn(
+    text: &str,
+    plies: usize,
+    movegen: &MoveGenerator,
+) -> Result<Vec<Opening>, String> {
+    let mut openings = vec![];
+
+    for (number, game) in pgn::split_games(text).iter().enumerate() {
+        let game = pgn::import(game, movegen).map_err(|e| format!("Game {}: {}", number + 1, e))?;
+        let moves = game
+            .line()
+            .into_iter()
+            .filter_map(|node| game.nodes[node].mv)
+            .take(plies)
+            .map(move_to_uci)
+            .collect();
+
+        let name = match (game.header("Opening"), game.header("Variation")) {
+            (Some(opening), Some(variation)) => format!("{}, {}", opening, variation),
+            (Some(opening), None) => opening.to_string(),
+            _ => format!("Game {}", number + 1),
+        };
+        openings.push(Opening {
+            name,
+            fen: game.start_fen.clone(),
+            moves,
+        });
+    }
+
+    Ok(openings)
+}
+
+// A game set up from the opening, with its moves played, and the position
+// after them. Moves that turn out to be illegal end the opening early.
+pub fn start_game(opening: &Opening, movegen: &MoveGenerator) -> (GameTree, Board) {
+    let mut game = GameTree::new(&opening.fen);
+    let mut board = game.board_at(0, movegen);
+
+    for uci in &opening.moves {
+        let Some(m) = uci_to_move(&board, movegen, uci) else {
+            eprintln!("Illegal move {} in opening {}", uci, opening.name);
+            break;
+        };
+        let san = move_to_san(&board, movegen, m);
+        board.make_move(m, movegen);
+        game.play(m, san);
+    }
+
+    (game, board)
+}
+
+// The openings for the given number of pairs of games. A suite with fewer
+// openings is played again from the start.
+pub fn choose(
+    mut openings: Vec<Opening>,
+    count: usize,
+    order: OpeningOrder,
+    seed: u64,
+) -> Vec<Opening> {
+    if order == OpeningOrder::Random {
+        openings.shuffle(&mut ChaChaRng::seed_from_u64(seed));
+    }
+    openings.truncate(count);
+
+    openings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::game::START_FEN;
+    use super::*;
+
+    #[test]
+    fn epd_suites() {
+        let text = "\
+rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - id \"King's pawn\";
+# a comment
+
+r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - c0 \"no id\";
+";
+        let openings = parse_epd(text).unwrap();
+        assert_eq!(openings.len(), 2);
+        assert_eq!(openings[0].name, "King's pawn");
+        assert_eq!(
+            openings[0].fen,
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"
+        );
+        assert_eq!(openings[1].name, "Position 4");
+        let full = parse_epd("8/8/8/4k3/8/8/8/4K3 w - - 12 40").unwrap();
+        assert_eq!(full[0].fen, "8/8/8/4k3/8/8/8/4K3 w - - 12 40");
+        assert!(parse_epd("not a position").is_err());
+    }
+
+    #[test]
+    fn pgn_suites() {
+        let text = "\
+[Event \"?\"]
+[Opening \"Ruy Lopez\"]
+
+1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 *
+
+[Event \"?\"]
+[SetUp \"1\"]
+[FEN \"4k3/8/8/8/8/8/4P3/4K3 w - - 0 1\"]
+
+1. e4 Kd7 *
+";
+        let openings = parse_pgn(text, 4, &MoveGenerator::new()).unwrap();
+        assert_eq!(openings.len(), 2);
+        assert_eq!(openings[0].name, "Ruy Lopez");
+        assert_eq!(openings[0].moves, ["e2e4", "e7e5", "g1f3", "b8c6"]);
+        assert_eq!(openings[1].fen, "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1");
+        assert_eq!(openings[1].moves, ["e2e4", "e8d7"]);
+    }
+
+    #[test]
+    fn choosing_openings() {
+        let suite: Vec<Opening> = (0..10)
+            .map(|n| Opening {
+                name: n.to_string(),
+                fen: START_FEN.to_string(),
+                moves: vec![],
+            })
+            .collect();
+
+        let sequential = choose(suite.clone(), 3, OpeningOrder::Sequential, 0);
+        let names: Vec<&str> = sequential.iter().map(|o| o.name.as_str()).collect();
+        assert_eq!(names, ["0", "1", "2"]);
+
+        let random = choose(suite.clone(), 20, OpeningOrder::Random, 7);
+        assert_eq!(random.len(), 10);
+        assert_eq!(random, choose(suite.clone(), 20, OpeningOrder::Random, 7));
+        assert_ne!(random, suite);
+    }
+}
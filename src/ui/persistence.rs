@@ -0,0 +1,127 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use super::config::UIConfig;
+use super::theme::PieceSet;
+use super::tournament::Tournament;
+
+const APP_DIRECTORY: &str = "chess-ui";
+const CONFIG_FILE: &str = "config.json";
+const TOURNAMENT_FILE: &str = "tournament.json";
+const TOURNAMENT_LOG: &str = "tournament_log.txt";
+
+// The config file lives in the platform's config directory, e.g.
+// ~/.config/chess-ui/config.json on Linux.
+pub fn config_path() -> Option<PathBuf> {
+    app_path(CONFIG_FILE)
+}
+
+// The running tournament is kept next to the config file, so it can be
+// resumed after the app was closed.
+pub fn tournament_path() -> Option<PathBuf> {
+    app_path(TOURNAMENT_FILE)
+}
+
+pub fn tournament_log_path() -> Option<PathBuf> {
+    app_path(TOURNAMENT_LOG)
+}
+
+fn app_path(file: &str) -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_DIRECTORY).join(file))
+}
+
+// Load the user's settings. A missing file gives the defaults, a file that
+// can't be read is kept aside as config.json.bak so the user doesn't lose it,
+// and the defaults are used instead.
+pub fn load() -> UIConfig {
+    let Some(path) = config_path() else {
+        return UIConfig::default();
+    };
+
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return UIConfig::default(),
+        Err(e) => {
+            eprintln!("Could not read config file {}: {}", path.display(), e);
+            return UIConfig::default();
+        }
+    };
+
+    match serde_json::from_str::<UIConfig>(&contents) {
+        Ok(mut config) => {
+            // A piece set that has been removed since falls back to the
+            // embedded one.
+            if let Some(directory) = &config.theme.pieces.directory {
+                if !directory.is_dir() {
+                    config.theme.pieces = PieceSet::embedded();
+                }
+            }
+            config
+        }
+        Err(e) => {
+            eprintln!("Config file {} is corrupt: {}", path.display(), e);
+            let _ = fs::rename(&path, path.with_extension("json.bak"));
+            UIConfig::default()
+        }
+    }
+}
+
+// Write the settings to the config file.
+pub fn save(config: &UIConfig) -> io::Result<()> {
+    let Some(path) = config_path() else {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "No config directory on this platform",
+        ));
+    };
+
+    let contents = serde_json::to_string_pretty(config).map_err(io::Error::other)?;
+    write_file(&path, &contents)
+}
+
+// The tournament that was saved last, if there is one that can be read.
+pub fn load_tournament() -> Option<Tournament> {
+    let contents = fs::read_to_string(tournament_path()?).ok()?;
+    match serde_json::from_str(&contents) {
+        Ok(tournament) => Some(tournament),
+        Err(e) => {
+            eprintln!("Could not read the saved tournament: {}", e);
+            None
+        }
+    }
+}
+
+pub fn save_tournament(tournament: &Tournament) -> io::Result<()> {
+    let path = tournament_path().ok_or(io::ErrorKind::NotFound)?;
+    let contents = serde_json::to_string_pretty(tournament).map_err(io::Error::other)?;
+    write_file(&path, &contents)
+}
+
+// Add lines to the tournament log. A new tournament starts a new log.
+pub fn log_tournament(text: &str, new_log: bool) -> io::Result<()> {
+    let path = tournament_log_path().ok_or(io::ErrorKind::NotFound)?;
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+
+    let mut log = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(!new_log)
+        .truncate(new_log)
+        .open(path)?;
+    writeln!(log, "{}", text)
+}
+
+// The file is written next to the old one first and then moved over it, so a
+// crash never leaves half a file.
+fn write_file(path: &Path, contents: &str) -> io::Result<()> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+
+    let temporary = path.with_extension("json.tmp");
+    fs::write(&temporary, contents)?;
+    fs::rename(&temporary, path)
+}
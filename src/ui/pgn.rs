@@ -0,0 +1,477 @@
+use super::game::{
+    AnnotationColor, Annotations, Arrow, GameNode, GameTree, NodeId, SquareMark, START_FEN,
+};
+use super::notation::{move_to_san, san_to_move};
+use super::uci::Score;
+use crate::board::defs::SQUARE_NAME;
+use crate::extra::parse::algebraic_square_to_number;
+use crate::movegen::MoveGenerator;
+
+const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+#[derive(Debug)]
+pub enum PgnError {
+    IllegalMove(String),
+    InvalidFen(String),
+    UnbalancedVariation,
+}
+
+impl std::fmt::Display for PgnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PgnError::IllegalMove(san) => write!(f, "Illegal move in PGN: {}", san),
+            PgnError::InvalidFen(fen) => write!(f, "Invalid FEN in PGN: {}", fen),
+            PgnError::UnbalancedVariation => write!(f, "Unbalanced variation in PGN"),
+        }
+    }
+}
+
+// Write the game with all its variations, comments and annotations.
+pub fn export(game: &GameTree) -> String {
+    let mut pgn = String::new();
+    let result = game.header("Result").unwrap_or("*").to_string();
+
+    for key in SEVEN_TAG_ROSTER {
+        let value = game.header(key).unwrap_or(match key {
+            "Date" => "????.??.??",
+            "Result" => "*",
+            _ => "?",
+        });
+        pgn.push_str(&format!("[{} \"{}\"]\n", key, value));
+    }
+    for (key, value) in &game.headers {
+        if !SEVEN_TAG_ROSTER.contains(&key.as_str()) && key != "SetUp" && key != "FEN" {
+            pgn.push_str(&format!("[{} \"{}\"]\n", key, value));
+        }
+    }
+    if game.start_fen != START_FEN {
+        pgn.push_str(&format!("[SetUp \"1\"]\n[FEN \"{}\"]\n", game.start_fen));
+    }
+    pgn.push('\n');
+
+    let mut movetext = String::new();
+    let root_comment = comment_text(game, 0);
+    if !root_comment.is_empty() {
+        movetext.push_str(&format!("{{{}}} ", root_comment));
+    }
+
+    write_line(game, 0, game.first_ply(), true, &mut movetext);
+    movetext.push_str(&result);
+
+    pgn.push_str(&wrap(&movetext, 80));
+    pgn.push('\n');
+
+    pgn
+}
+
+// Write the main line from the given node, with the alternatives to each move
+// as variations in parentheses.
+fn write_line(game: &GameTree, node: NodeId, ply: usize, numbered: bool, out: &mut String) {
+    let children = &game.nodes[node].children;
+    let Some(&main) = children.first() else {
+        return;
+    };
+
+    write_move(game, main, ply, numbered, out);
+    for &variation in &children[1..] {
+        out.push('(');
+        write_move(game, variation, ply, true, out);
+        write_line(game, variation, ply + 1, has_comment(game, variation), out);
+        trim_end(out);
+        out.push_str(") ");
+    }
+
+    // Black's move needs its number again after a comment or variation.
+    let numbered = children.len() > 1 || has_comment(game, main);
+    write_line(game, main, ply + 1, numbered, out);
+}
+
+fn has_comment(game: &GameTree, node: NodeId) -> bool {
+    let node = &game.nodes[node];
+    !node.comment.is_empty() || !node.annotations.is_empty() || node.score.is_some()
+}
+
+fn write_move(game: &GameTree, node: NodeId, ply: usize, numbered: bool, out: &mut String) {
+    let move_number = ply / 2 + 1;
+    if ply.is_multiple_of(2) {
+        out.push_str(&format!("{}. ", move_number));
+    } else if numbered {
+        out.push_str(&format!("{}... ", move_number));
+    }
+
+    out.push_str(&game.nodes[node].san);
+    out.push(' ');
+
+    let comment = comment_text(game, node);
+    if !comment.is_empty() {
+        out.push_str(&format!("{{{}}} ", comment));
+    }
+}
+
+fn comment_text(game: &GameTree, node: NodeId) -> String {
+    let GameNode {
+        comment,
+        annotations,
+        score,
+        ..
+    } = &game.nodes[node];
+    let mut parts = vec![];
+
+    if let Some(score) = score {
+        parts.push(format!(
+            "[%eval {}]",
+            eval_text(*score, game.white_to_move(node))
+        ));
+    }
+    if !annotations.squares.is_empty() {
+        let squares: Vec<String> = annotations
+            .squares
+            .iter()
+            .map(|s| format!("{}{}", s.color.letter(), SQUARE_NAME[s.square]))
+            .collect();
+        parts.push(format!("[%csl {}]", squares.join(",")));
+    }
+    if !annotations.arrows.is_empty() {
+        let arrows: Vec<String> = annotations
+            .arrows
+            .iter()
+            .map(|a| {
+                format!(
+                    "{}{}{}",
+                    a.color.letter(),
+                    SQUARE_NAME[a.from],
+                    SQUARE_NAME[a.to]
+                )
+            })
+            .collect();
+        parts.push(format!("[%cal {}]", arrows.join(",")));
+    }
+    if !comment.is_empty() {
+        parts.push(comment.to_string());
+    }
+
+    parts.join(" ")
+}
+
+// A score as used by [%eval], from white's point of view: pawns like
+// "-0.35", or "#3" and "#-3" for mates.
+fn eval_text(score: Score, white_to_move: bool) -> String {
+    let text = score.white_text(white_to_move);
+    match text.strip_prefix('-').unwrap_or(&text).strip_prefix('M') {
+        Some(moves) if text.starts_with('-') => format!("#-{}", moves),
+        Some(moves) => format!("#{}", moves),
+        None => text.trim_start_matches('+').to_string(),
+    }
+}
+
+// The score of an [%eval] from the side to move's point of view.
+fn parse_eval(text: &str, white_to_move: bool) -> Option<Score> {
+    let sign = if white_to_move { 1 } else { -1 };
+    match text.strip_prefix('#') {
+        Some(moves) => Some(Score::Mate(sign * moves.parse::<i32>().ok()?)),
+        None => Some(Score::Cp(
+            sign * (text.parse::<f32>().ok()? * 100.0).round() as i32,
+        )),
+    }
+}
+
+fn trim_end(out: &mut String) {
+    while out.ends_with(' ') {
+        out.pop();
+    }
+}
+
+fn wrap(text: &str, width: usize) -> String {
+    let mut wrapped = String::new();
+    let mut line_length = 0;
+
+    for word in text.split_whitespace() {
+        if line_length > 0 && line_length + word.len() + 1 > width {
+            wrapped.push('\n');
+            line_length = 0;
+        } else if line_length > 0 {
+            wrapped.push(' ');
+            line_length += 1;
+        }
+        wrapped.push_str(word);
+        line_length += word.len();
+    }
+
+    wrapped
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Header(String, String),
+    Comment(String),
+    VariationStart,
+    VariationEnd,
+    Result(String),
+    Move(String),
+}
+
+fn tokenize(pgn: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut chars = pgn.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '[' => {
+                let tag: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                if let Some((key, value)) = tag.split_once(' ') {
+                    let value = value.trim().trim_matches('"').to_string();
+                    tokens.push(Token::Header(key.trim().to_string(), value));
+                }
+            }
+            '{' => {
+                let comment: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                tokens.push(Token::Comment(comment.trim().to_string()));
+            }
+            ';' => {
+                let comment: String = chars.by_ref().take_while(|&c| c != '\n').collect();
+                tokens.push(Token::Comment(comment.trim().to_string()));
+            }
+            '(' => tokens.push(Token::VariationStart),
+            ')' => tokens.push(Token::VariationEnd),
+            c if c.is_whitespace() => {}
+            c => {
+                let mut word = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || "{}();[".contains(next) {
+                        break;
+                    }
+                    word.push(next);
+                    chars.next();
+                }
+
+                match word.as_str() {
+                    "1-0" | "0-1" | "1/2-1/2" | "*" => tokens.push(Token::Result(word)),
+                    // NAGs are not kept
+                    w if w.starts_with('$') => {}
+                    w => {
+                        // Move numbers can be glued to the move, as in "1.e4".
+                        let san = match w.rfind('.') {
+                            Some(i) => &w[i + 1..],
+                            None => w,
+                        };
+                        if !san.is_empty() && !san.chars().all(|c| c.is_ascii_digit()) {
+                            tokens.push(Token::Move(san.to_string()));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    tokens
+}
+
+// Read the first game of a PGN text, including variations, comments and
+// annotations.
+pub fn import(pgn: &str, movegen: &MoveGenerator) -> Result<GameTree, PgnError> {
+    let tokens = tokenize(pgn);
+
+    let fen = tokens
+        .iter()
+        .find_map(|t| match t {
+            Token::Header(key, value) if key == "FEN" => Some(value.clone()),
+            _ => None,
+        })
+        .unwrap_or_else(|| START_FEN.to_string());
+
+    let mut game = GameTree::new(&fen);
+    let mut board = game.board_at(0, movegen);
+    if board.create_fen().split_whitespace().next() != fen.split_whitespace().next() {
+        return Err(PgnError::InvalidFen(fen));
+    }
+
+    // Nodes to return to when a variation ends.
+    let mut stack: Vec<NodeId> = vec![];
+
+    for token in tokens {
+        match token {
+            Token::Header(key, value) => {
+                if key != "FEN" && key != "SetUp" {
+                    game.set_header(&key, &value);
+                }
+            }
+            Token::Comment(text) => {
+                let (comment, annotations, eval) = parse_comment(&text);
+                let white_to_move = game.white_to_move(game.current);
+                let node = game.current_node_mut();
+                if let Some(score) = eval.and_then(|eval| parse_eval(&eval, white_to_move)) {
+                    node.score = Some(score);
+                }
+                node.annotations.arrows.extend(annotations.arrows);
+                node.annotations.squares.extend(annotations.squares);
+                if !comment.is_empty() {
+                    if !node.comment.is_empty() {
+                        node.comment.push(' ');
+                    }
+                    node.comment.push_str(&comment);
+                }
+            }
+            Token::VariationStart => {
+                // A variation replaces the move that was just played.
+                stack.push(game.current);
+                game.back();
+                board = game.board_at(game.current, movegen);
+            }
+            Token::VariationEnd => {
+                let node = stack.pop().ok_or(PgnError::UnbalancedVariation)?;
+                game.go_to(node);
+                board = game.board_at(game.current, movegen);
+            }
+            Token::Result(result) => {
+                if stack.is_empty() {
+                    game.set_header("Result", &result);
+                    break;
+                }
+            }
+            Token::Move(san) => {
+                let mv = san_to_move(&board, movegen, &san)
+                    .ok_or_else(|| PgnError::IllegalMove(san.clone()))?;
+                let san = move_to_san(&board, movegen, mv);
+                board.make_move(mv, movegen);
+                game.play(mv, san);
+            }
+        }
+    }
+
+    if !stack.is_empty() {
+        return Err(PgnError::UnbalancedVariation);
+    }
+
+    game.to_start();
+    Ok(game)
+}
+
+// Split a PGN file into its games. A game starts with its headers, after the
+// moves of the game before.
+pub fn split_games(pgn: &str) -> Vec<String> {
+    let mut games = vec![];
+    let mut game = String::new();
+    let mut has_moves = false;
+
+    for line in pgn.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && has_moves {
+            games.push(std::mem::take(&mut game));
+            has_moves = false;
+        }
+        if !trimmed.is_empty() && !trimmed.starts_with('[') {
+            has_moves = true;
+        }
+        game.push_str(line);
+        game.push('\n');
+    }
+    if !game.trim().is_empty() {
+        games.push(game);
+    }
+
+    games
+}
+
+// Split a comment into its text, the [%cal] and [%csl] commands and the
+// argument of the [%eval] command in it.
+fn parse_comment(text: &str) -> (String, Annotations, Option<String>) {
+    let mut annotations = Annotations::default();
+    let mut eval = None;
+    let mut comment = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find("[%") {
+        comment.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find(']') else {
+            rest = &rest[start..];
+            break;
+        };
+
+        let command = &rest[start + 2..start + end];
+        let (name, args) = command.split_once(' ').unwrap_or((command, ""));
+        if name == "eval" {
+            eval = Some(args.trim().to_string());
+        }
+        for arg in args.split(',').map(str::trim).filter(|a| !a.is_empty()) {
+            let Some(color) = arg.chars().next().and_then(AnnotationColor::from_letter) else {
+                continue;
+            };
+            match (name, arg.get(1..3), arg.get(3..5)) {
+                ("csl", Some(square), None) => {
+                    if let Some(square) = algebraic_square_to_number(square) {
+                        annotations.squares.push(SquareMark { square, color });
+                    }
+                }
+                ("cal", Some(from), Some(to)) => {
+                    if let (Some(from), Some(to)) = (
+                        algebraic_square_to_number(from),
+                        algebraic_square_to_number(to),
+                    ) {
+                        annotations.arrows.push(Arrow { from, to, color });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        rest = &rest[start + end + 1..];
+    }
+    comment.push_str(rest);
+
+    (
+        comment.split_whitespace().collect::<Vec<_>>().join(" "),
+        annotations,
+        eval,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_with_variations_and_annotations() {
+        let movegen = MoveGenerator::new();
+        let pgn = "[Event \"Test\"]\n\n1. e4 {[%csl Gd5][%cal Ge2e4,Rg8f6] Best by test} e5 \
+                   (1... c5 2. Nf3) 2. Nf3 Nc6 3. Bb5 a6 4. O-O *";
+
+        let game = import(pgn, &movegen).unwrap();
+        let e4 = game.nodes[0].children[0];
+        assert_eq!(game.nodes[e4].san, "e4");
+        assert_eq!(game.nodes[e4].comment, "Best by test");
+        assert_eq!(game.nodes[e4].annotations.arrows.len(), 2);
+        assert_eq!(game.nodes[e4].annotations.squares.len(), 1);
+        assert_eq!(game.nodes[e4].children.len(), 2);
+
+        let exported = export(&game);
+        assert!(exported.contains("[Event \"Test\"]"));
+        assert!(exported.replace('\n', " ").contains(
+            "1. e4 {[%csl Gd5] [%cal Ge2e4,Rg8f6] Best by test} 1... e5 (1... c5 2. Nf3) 2. Nf3 \
+             Nc6 3. Bb5 a6 4. O-O *"
+        ));
+
+        let again = import(&exported, &movegen).unwrap();
+        assert_eq!(export(&again), exported);
+    }
+
+    #[test]
+    fn evals_are_kept_from_whites_point_of_view() {
+        let movegen = MoveGenerator::new();
+        let pgn = "1. e4 {[%eval 0.3]} e5 {[%eval 0.25]} 2. Qh5 {[%eval -2.5]} Nc6 {[%eval #2]} *";
+
+        let game = import(pgn, &movegen).unwrap();
+        let line = {
+            let mut game = game.clone();
+            game.to_end();
+            game.line()
+        };
+        assert_eq!(game.nodes[line[0]].score, Some(Score::Cp(-30)));
+        assert_eq!(game.nodes[line[3]].score, Some(Score::Mate(2)));
+        assert!(game.is_blunder(line[2]));
+        assert!(!game.is_blunder(line[1]));
+
+        let exported = export(&game).replace('\n', " ");
+        assert!(exported.contains("1. e4 {[%eval 0.30]} 1... e5 {[%eval 0.25]}"));
+        assert!(exported.contains("2. Qh5 {[%eval -2.50]} 2... Nc6 {[%eval #2]}"));
+    }
+}
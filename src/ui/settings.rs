@@ -1,9 +1,13 @@
-use super::config::GameMode;
+use super::config::{GameMode, SearchLimit, TimeControl, TimeControlKind};
+use super::theme::{AppTheme, BoardStyle, PieceSet, UiTheme};
 use super::{config::UIConfig, ui::Message, ui::Tab};
 use iced::widget::{
-    column, row, Button, Checkbox, Column, Container, PickList, Scrollable, Text, TextInput,
+    column, row, Checkbox, Column, Container, PickList, Scrollable, Text, TextInput,
 };
 use iced::{alignment, Alignment, Command, Element, Length, Theme};
+use iced_aw::TabLabel;
+
+const MAX_MULTIPV: u32 = 10;
 
 #[derive(Clone, Debug)]
 pub enum SettingsMessage {
@@ -11,6 +15,21 @@ pub enum SettingsMessage {
     CheckShowCoords(bool),
     CheckSetDepth(u32),
     SelectSetGameMode(GameMode),
+    SelectPlayerSide(usize),
+    SelectBoardStyle(BoardStyle),
+    SelectPieceSet(PieceSet),
+    SelectAppTheme(AppTheme),
+    SelectTimeControl(TimeControlKind),
+    SetBaseTime(String),
+    SetIncrement(String),
+    SetMovesPerPeriod(String),
+    SetMoveTime(String),
+    SelectSearchLimit(SearchLimit),
+    SetSearchDepth(String),
+    SetSearchNodes(String),
+    SetSearchMoveTime(String),
+    SetSafetyMargin(String),
+    SetMultiPv(String),
 }
 
 pub struct SettingsTab {
@@ -19,103 +38,227 @@ pub struct SettingsTab {
     pub search_depth: u32,
     pub game_mode: GameMode,
     pub player_side: u32,
+    pub theme: UiTheme,
+    pub piece_sets: Vec<PieceSet>,
+    pub time_control: TimeControl,
+    pub search_limit: SearchLimit,
+    pub search_nodes: u64,
+    pub search_move_time_ms: u64,
+    pub safety_margin_ms: u64,
+    pub multipv: u32,
 }
 
 impl SettingsTab {
-    pub fn new() -> Self {
+    pub fn from_config(config: UIConfig) -> Self {
         SettingsTab {
-            flip_board: false,
-            show_coords: true,
-            search_depth: 3,
-            game_mode: GameMode::EngineEngine,
-            player_side: 0,
+            flip_board: config.flip_board,
+            show_coords: config.show_coordinates,
+            search_depth: config.search_depth,
+            game_mode: config.game_mode,
+            player_side: config.player_side,
+            theme: config.theme,
+            piece_sets: PieceSet::available(),
+            time_control: config.time_control,
+            search_limit: config.search_limit,
+            search_nodes: config.search_nodes,
+            search_move_time_ms: config.search_move_time_ms,
+            safety_margin_ms: config.safety_margin_ms,
+            multipv: config.multipv,
+        }
+    }
+
+    pub fn config(&self) -> UIConfig {
+        UIConfig {
+            show_coordinates: self.show_coords,
+            flip_board: self.flip_board,
+            search_depth: self.search_depth,
+            game_mode: self.game_mode,
+            player_side: self.player_side,
+            theme: self.theme.clone(),
+            time_control: self.time_control,
+            search_limit: self.search_limit,
+            search_nodes: self.search_nodes,
+            search_move_time_ms: self.search_move_time_ms,
+            safety_margin_ms: self.safety_margin_ms,
+            multipv: self.multipv,
+            ..UIConfig::default()
         }
     }
 
     pub fn update(&mut self, message: SettingsMessage) -> Command<Message> {
         match message {
-            SettingsMessage::CheckFlipBoard(value) => {
-                self.flip_board = value;
-                Command::perform(
-                    SettingsTab::send_changes(
-                        self.flip_board,
-                        self.show_coords,
-                        self.search_depth,
-                        self.game_mode,
-                        self.player_side,
-                    ),
-                    Message::ChangeSettings,
-                )
+            SettingsMessage::CheckFlipBoard(value) => self.flip_board = value,
+            SettingsMessage::CheckShowCoords(value) => self.show_coords = value,
+            SettingsMessage::CheckSetDepth(value) => self.search_depth = value,
+            SettingsMessage::SelectSetGameMode(value) => self.game_mode = value,
+            SettingsMessage::SelectPlayerSide(value) => self.player_side = value as u32,
+            SettingsMessage::SelectBoardStyle(value) => self.theme.board = value,
+            SettingsMessage::SelectPieceSet(value) => self.theme.pieces = value,
+            SettingsMessage::SelectAppTheme(value) => self.theme.app = value,
+            SettingsMessage::SelectTimeControl(value) => self.time_control.kind = value,
+            SettingsMessage::SetBaseTime(value) => {
+                self.time_control.base_ms = parse_number(&value).saturating_mul(60 * 1000);
             }
-            SettingsMessage::CheckShowCoords(value) => {
-                self.show_coords = value;
-                Command::perform(
-                    SettingsTab::send_changes(
-                        self.flip_board,
-                        self.show_coords,
-                        self.search_depth,
-                        self.game_mode,
-                        self.player_side,
-                    ),
-                    Message::ChangeSettings,
-                )
+            SettingsMessage::SetIncrement(value) => {
+                self.time_control.increment_ms = parse_number(&value).saturating_mul(1000);
             }
-            SettingsMessage::CheckSetDepth(value) => {
-                self.search_depth = value;
-                Command::perform(
-                    SettingsTab::send_changes(
-                        self.flip_board,
-                        self.show_coords,
-                        self.search_depth,
-                        self.game_mode,
-                        self.player_side,
-                    ),
-                    Message::ChangeSettings,
-                )
+            SettingsMessage::SetMovesPerPeriod(value) => {
+                self.time_control.moves_per_period = (parse_number(&value) as u32).max(1);
             }
-            SettingsMessage::SelectSetGameMode(value) => {
-                self.game_mode = value;
-                Command::perform(
-                    SettingsTab::send_changes(
-                        self.flip_board,
-                        self.show_coords,
-                        self.search_depth,
-                        self.game_mode,
-                        self.player_side,
-                    ),
-                    Message::ChangeSettings,
-                )
+            SettingsMessage::SetMoveTime(value) => {
+                self.time_control.move_time_ms = parse_number(&value).max(1).saturating_mul(1000);
+            }
+            SettingsMessage::SelectSearchLimit(value) => self.search_limit = value,
+            SettingsMessage::SetSearchDepth(value) => {
+                self.search_depth = (parse_number(&value) as u32).max(1);
+            }
+            SettingsMessage::SetSearchNodes(value) => {
+                self.search_nodes = parse_number(&value).max(1);
+            }
+            SettingsMessage::SetSearchMoveTime(value) => {
+                self.search_move_time_ms = parse_number(&value).max(1);
+            }
+            SettingsMessage::SetSafetyMargin(value) => {
+                self.safety_margin_ms = parse_number(&value);
+            }
+            SettingsMessage::SetMultiPv(value) => {
+                self.multipv = (parse_number(&value) as u32).clamp(1, MAX_MULTIPV);
             }
         }
+
+        Command::perform(
+            SettingsTab::send_changes(self.config()),
+            Message::ChangeSettings,
+        )
     }
 
-    pub async fn send_changes(
-        flip: bool,
-        coords: bool,
-        depth: u32,
-        game_mode: GameMode,
-        side: u32,
-    ) -> Option<UIConfig> {
-        let mut config = UIConfig::default();
-        config.flip_board = flip;
-        config.show_coordinates = coords;
-        config.search_depth = depth;
-        config.game_mode = game_mode;
-        config.player_side = side;
+    // Only the fields the selected kind of time control uses are shown.
+    fn time_control_view(&self) -> Element<'_, SettingsMessage, iced::Renderer<Theme>> {
+        let time_control = &self.time_control;
+        let minutes = TextInput::new("5", &(time_control.base_ms / 60000).to_string())
+            .on_input(SettingsMessage::SetBaseTime)
+            .width(60);
+        let increment = TextInput::new("0", &(time_control.increment_ms / 1000).to_string())
+            .on_input(SettingsMessage::SetIncrement)
+            .width(60);
+
+        let fields = match time_control.kind {
+            TimeControlKind::SuddenDeath => row![Text::new("Minutes: "), minutes],
+            TimeControlKind::Increment => row![
+                Text::new("Minutes: "),
+                minutes,
+                Text::new("Increment (s): "),
+                increment,
+            ],
+            TimeControlKind::MovesPerPeriod => row![
+                Text::new("Moves: "),
+                TextInput::new("40", &time_control.moves_per_period.to_string())
+                    .on_input(SettingsMessage::SetMovesPerPeriod)
+                    .width(60),
+                Text::new("Minutes: "),
+                minutes,
+                Text::new("Increment (s): "),
+                increment,
+            ],
+            TimeControlKind::FixedPerMove => row![
+                Text::new("Seconds per move: "),
+                TextInput::new("10", &(time_control.move_time_ms / 1000).to_string())
+                    .on_input(SettingsMessage::SetMoveTime)
+                    .width(60),
+            ],
+        };
+
+        column![
+            row![
+                Text::new("Time control: "),
+                PickList::new(
+                    &TimeControlKind::ALL[..],
+                    Some(time_control.kind),
+                    SettingsMessage::SelectTimeControl
+                )
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+            fields.spacing(10).align_items(Alignment::Center),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center)
+        .into()
+    }
+
+    // The limit playing engines search with, and the value for it.
+    fn search_limit_view(&self) -> Element<'_, SettingsMessage, iced::Renderer<Theme>> {
+        let value = match self.search_limit {
+            SearchLimit::Clock => row![
+                Text::new("Safety margin (ms): "),
+                TextInput::new("50", &self.safety_margin_ms.to_string())
+                    .on_input(SettingsMessage::SetSafetyMargin)
+                    .width(60),
+            ],
+            SearchLimit::MoveTime => row![
+                Text::new("Milliseconds: "),
+                TextInput::new("1000", &self.search_move_time_ms.to_string())
+                    .on_input(SettingsMessage::SetSearchMoveTime)
+                    .width(80),
+            ],
+            SearchLimit::Nodes => row![
+                Text::new("Nodes: "),
+                TextInput::new("1000000", &self.search_nodes.to_string())
+                    .on_input(SettingsMessage::SetSearchNodes)
+                    .width(100),
+            ],
+            SearchLimit::Depth => row![
+                Text::new("Depth: "),
+                TextInput::new("3", &self.search_depth.to_string())
+                    .on_input(SettingsMessage::SetSearchDepth)
+                    .width(60),
+            ],
+        };
+
+        column![
+            row![
+                Text::new("Engine search: "),
+                PickList::new(
+                    &SearchLimit::ALL[..],
+                    Some(self.search_limit),
+                    SettingsMessage::SelectSearchLimit
+                )
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+            value.spacing(10).align_items(Alignment::Center),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center)
+        .into()
+    }
+
+    pub async fn send_changes(config: UIConfig) -> Option<UIConfig> {
         Some(config)
     }
 }
 
+// Digits typed in a number field, where an empty field counts as zero and
+// too many digits as the largest number.
+pub(super) fn parse_number(value: &str) -> u64 {
+    let digits: String = value.chars().filter(|c| c.is_ascii_digit()).collect();
+    if digits.is_empty() {
+        return 0;
+    }
+
+    digits.parse().unwrap_or(u64::MAX)
+}
+
 impl Tab for SettingsTab {
     type Message = Message;
 
     fn title(&self) -> String {
-        "Chess engine".to_string()
+        "Settings".to_string()
     }
 
-    // fn tab_label(&self) -> TabLabel {
-    //     TabLabel::Text(self.title())
-    // }
+    fn tab_label(&self) -> TabLabel {
+        TabLabel::Text(self.title())
+    }
 
     fn content(&self) -> iced::Element<Message, iced::Renderer<iced::Theme>> {
         let col_settings = column![
@@ -141,12 +284,52 @@ impl Tab for SettingsTab {
             ]
             .spacing(10)
             .align_items(Alignment::Center),
+            self.time_control_view(),
+            self.search_limit_view(),
+            row![
+                Text::new("Analysis lines: "),
+                TextInput::new("3", &self.multipv.to_string())
+                    .on_input(SettingsMessage::SetMultiPv)
+                    .width(60),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+            row![
+                Text::new("Board: "),
+                PickList::new(
+                    &BoardStyle::ALL[..],
+                    Some(self.theme.board),
+                    SettingsMessage::SelectBoardStyle
+                )
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+            row![
+                Text::new("Pieces: "),
+                PickList::new(
+                    &self.piece_sets[..],
+                    Some(self.theme.pieces.clone()),
+                    SettingsMessage::SelectPieceSet
+                )
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+            row![
+                Text::new("Theme: "),
+                PickList::new(
+                    &AppTheme::ALL[..],
+                    Some(self.theme.app),
+                    SettingsMessage::SelectAppTheme
+                )
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
         ]
         .spacing(10)
         .align_items(Alignment::Center);
 
         let content: Element<SettingsMessage, iced::Renderer<Theme>> = Container::new(
-            Scrollable::new(Column::new().height(200).spacing(10).push(col_settings)),
+            Scrollable::new(Column::new().height(450).spacing(10).push(col_settings)),
         )
         .align_x(alignment::Horizontal::Center)
         .height(Length::Fill)
@@ -0,0 +1,417 @@
+// Statistics about the results of an engine against its opponents, to tell
+// whether it is stronger.
+
+use serde::{Deserialize, Serialize};
+
+// The score for a 95% confidence interval, in standard deviations.
+const Z_95: f64 = 1.959964;
+
+// The results of an engine, counted per game and per pair of games played
+// with the same opening.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MatchStats {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    // Pairs by the half points the engine scored in them, from 0 to 4.
+    pub pentanomial: [usize; 5],
+}
+
+// An Elo difference with the margin of its 95% confidence interval.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EloEstimate {
+    pub elo: f64,
+    pub error: f64,
+}
+
+impl MatchStats {
+    pub fn add_game(&mut self, half_points: u32) {
+        match half_points {
+            2 => self.wins += 1,
+            1 => self.draws += 1,
+            _ => self.losses += 1,
+        }
+    }
+
+    pub fn add_pair(&mut self, half_points: u32) {
+        self.pentanomial[(half_points as usize).min(4)] += 1;
+    }
+
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    pub fn pairs(&self) -> usize {
+        self.pentanomial.iter().sum()
+    }
+
+    pub fn draw_ratio(&self) -> Option<f64> {
+        (self.games() > 0).then(|| self.draws as f64 / self.games() as f64)
+    }
+
+    // The Elo difference from the game results.
+    pub fn elo(&self) -> Option<EloEstimate> {
+        estimate(&[(0.0, self.losses), (0.5, self.draws), (1.0, self.wins)])
+    }
+
+    // The Elo difference from the results of the pairs of games. The games of
+    // a pair share an opening, so this takes out what the opening decided.
+    pub fn pentanomial_elo(&self) -> Option<EloEstimate> {
+        let scores = [0.0, 0.25, 0.5, 0.75, 1.0];
+        let counts: Vec<(f64, usize)> = scores.into_iter().zip(self.pentanomial).collect();
+        estimate(&counts)
+    }
+
+    // The likelihood of superiority, the chance the engine is stronger. Draws
+    // don't tell the engines apart, so only wins and losses count.
+    pub fn los(&self) -> Option<f64> {
+        let decisive = (self.wins + self.losses) as f64;
+        (decisive > 0.0).then(|| {
+            0.5 * (1.0 + erf((self.wins as f64 - self.losses as f64) / (2.0 * decisive).sqrt()))
+        })
+    }
+
+    // The statistics as they are shown and logged.
+    pub fn summary(&self) -> String {
+        let (Some(elo), Some(los), Some(draws)) = (self.elo(), self.los(), self.draw_ratio())
+        else {
+            return format!(
+                "{} games (+{} ={} -{})",
+                self.games(),
+                self.wins,
+                self.draws,
+                self.losses
+            );
+        };
+
+        let mut summary = format!(
+            "{} games (+{} ={} -{}), Elo {:+.1} +/- {:.1}, LOS {:.1} %, draws {:.1} %",
+            self.games(),
+            self.wins,
+            self.draws,
+            self.losses,
+            elo.elo,
+            elo.error,
+            los * 100.0,
+            draws * 100.0
+        );
+        if let Some(elo) = self.pentanomial_elo() {
+            let counts: Vec<String> = self.pentanomial.iter().map(|n| n.to_string()).collect();
+            summary.push_str(&format!(
+                "\nPentanomial [{}], Elo {:+.1} +/- {:.1}",
+                counts.join(", "),
+                elo.elo,
+                elo.error
+            ));
+        }
+
+        summary
+    }
+}
+
+// A sequential probability ratio test: is the engine elo1 stronger than its
+// opponents (H1), or no more than elo0 (H0)? Alpha and beta are the chances
+// of accepting the wrong one.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Default for Sprt {
+    fn default() -> Self {
+        Self {
+            elo0: 0.0,
+            elo1: 5.0,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SprtResult {
+    // The engine is elo1 stronger.
+    H1,
+    // The engine is no more than elo0 stronger.
+    H0,
+}
+
+impl std::fmt::Display for SprtResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                SprtResult::H1 => "H1 accepted",
+                SprtResult::H0 => "H0 accepted",
+            }
+        )
+    }
+}
+
+impl Sprt {
+    // The log likelihood ratios at which H0 and H1 are accepted.
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    // The log likelihood ratio of the game results.
+    pub fn llr(&self, stats: &MatchStats) -> Option<f64> {
+        self.log_likelihood_ratio(&[(0.0, stats.losses), (0.5, stats.draws), (1.0, stats.wins)])
+    }
+
+    // The log likelihood ratio of the results of the pairs of games.
+    pub fn pentanomial_llr(&self, stats: &MatchStats) -> Option<f64> {
+        let scores = [0.0, 0.25, 0.5, 0.75, 1.0];
+        let counts: Vec<(f64, usize)> = scores.into_iter().zip(stats.pentanomial).collect();
+        self.log_likelihood_ratio(&counts)
+    }
+
+    // The test is decided by the pairs of games when there are any, since
+    // they don't count what the openings decided.
+    pub fn result(&self, stats: &MatchStats) -> Option<SprtResult> {
+        let llr = if stats.pairs() > 0 {
+            self.pentanomial_llr(stats)
+        } else {
+            self.llr(stats)
+        }?;
+
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            Some(SprtResult::H1)
+        } else if llr <= lower {
+            Some(SprtResult::H0)
+        } else {
+            None
+        }
+    }
+
+    pub fn summary(&self, stats: &MatchStats) -> String {
+        let (lower, upper) = self.bounds();
+        let show = |llr: Option<f64>| llr.map_or("-".to_string(), |llr| format!("{:.2}", llr));
+
+        let mut summary = format!(
+            "SPRT elo0 {} elo1 {} alpha {} beta {}: LLR {} (pentanomial {}), bounds [{:.2}, {:.2}]",
+            self.elo0,
+            self.elo1,
+            self.alpha,
+            self.beta,
+            show(self.llr(stats)),
+            show(self.pentanomial_llr(stats)),
+            lower,
+            upper
+        );
+        if let Some(result) = self.result(stats) {
+            summary.push_str(&format!(", {}", result));
+        }
+
+        summary
+    }
+
+    // The generalized SPRT approximation: the log likelihood ratio of the
+    // mean score coming from elo1 rather than elo0, given its variance.
+    // Results that are all the same don't give a ratio yet.
+    fn log_likelihood_ratio(&self, counts: &[(f64, usize)]) -> Option<f64> {
+        let (n, mean, variance) = mean_and_variance(counts)?;
+        if variance <= 0.0 {
+            return None;
+        }
+
+        let score0 = score_from_elo(self.elo0);
+        let score1 = score_from_elo(self.elo1);
+        Some(n * (score1 - score0) * (2.0 * mean - score0 - score1) / (2.0 * variance))
+    }
+}
+
+// The Elo difference that gives the expected score.
+pub fn elo_from_score(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+// The expected score of an engine the Elo difference stronger.
+pub fn score_from_elo(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+// The number of results, their mean score and its variance, from the number
+// of times each score was reached.
+fn mean_and_variance(counts: &[(f64, usize)]) -> Option<(f64, f64, f64)> {
+    let total: usize = counts.iter().map(|&(_, count)| count).sum();
+    if total == 0 {
+        return None;
+    }
+
+    let n = total as f64;
+    let mean = counts
+        .iter()
+        .map(|&(score, count)| score * count as f64)
+        .sum::<f64>()
+        / n;
+    let variance = counts
+        .iter()
+        .map(|&(score, count)| (score - mean).powi(2) * count as f64)
+        .sum::<f64>()
+        / n;
+
+    Some((n, mean, variance))
+}
+
+// The Elo difference and its confidence interval from the number of times
+// each score was reached. Only wins or only losses don't give a finite
+// difference yet.
+fn estimate(counts: &[(f64, usize)]) -> Option<EloEstimate> {
+    let (n, mean, variance) = mean_and_variance(counts)?;
+    if mean <= 0.0 || mean >= 1.0 {
+        return None;
+    }
+    let margin = Z_95 * (variance / n).sqrt();
+
+    // After a few games the interval reaches a score of 0 or 1, which is an
+    // infinite difference, so it stops half a game short of them.
+    let edge = (0.5 / n).min(mean).min(1.0 - mean);
+    let low = elo_from_score((mean - margin).max(edge));
+    let high = elo_from_score((mean + margin).min(1.0 - edge));
+    Some(EloEstimate {
+        elo: elo_from_score(mean),
+        error: (high - low) / 2.0,
+    })
+}
+
+// The error function, as approximated by Abramowitz and Stegun (7.1.26).
+// Good to about 1e-7, which is plenty for a percentage.
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let polynomial = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let y = 1.0 - polynomial * (-x * x).exp();
+
+    if x < 0.0 {
+        -y
+    } else {
+        y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elo_and_los() {
+        let stats = MatchStats {
+            wins: 60,
+            draws: 20,
+            losses: 20,
+            ..MatchStats::default()
+        };
+        let elo = stats.elo().unwrap();
+        assert!((elo.elo - 147.19).abs() < 0.01);
+        assert!((elo.error - 66.0).abs() < 0.5);
+        assert!(stats.los().unwrap() > 0.9999);
+        assert_eq!(stats.draw_ratio(), Some(0.2));
+
+        let even = MatchStats {
+            wins: 10,
+            draws: 5,
+            losses: 10,
+            ..MatchStats::default()
+        };
+        assert_eq!(even.elo().unwrap().elo, 0.0);
+        assert!((even.los().unwrap() - 0.5).abs() < 1e-9);
+        assert_eq!(MatchStats::default().elo(), None);
+
+        // After only wins there is no finite Elo, and the summary only
+        // counts the games.
+        let mut wins = MatchStats::default();
+        wins.add_game(2);
+        wins.add_game(2);
+        wins.add_pair(4);
+        assert_eq!(wins.elo(), None);
+        assert_eq!(wins.pentanomial_elo(), None);
+        assert_eq!(wins.summary(), "2 games (+2 =0 -0)");
+
+        // A short match still gives a finite interval, and only draws no
+        // difference at all.
+        let short = MatchStats {
+            wins: 2,
+            losses: 1,
+            ..MatchStats::default()
+        };
+        assert!(short.elo().unwrap().error.is_finite());
+        let draws = MatchStats {
+            draws: 4,
+            ..MatchStats::default()
+        };
+        assert_eq!(
+            draws.elo(),
+            Some(EloEstimate {
+                elo: 0.0,
+                error: 0.0
+            })
+        );
+    }
+
+    #[test]
+    fn pentanomial() {
+        let mut stats = MatchStats::default();
+        for (half_points, count) in [(0, 1), (1, 2), (2, 3), (3, 2), (4, 1)] {
+            for _ in 0..count {
+                stats.add_pair(half_points);
+            }
+        }
+        assert_eq!(stats.pairs(), 9);
+        let elo = stats.pentanomial_elo().unwrap();
+        assert_eq!(elo.elo, 0.0);
+        assert!(elo.error > 0.0);
+    }
+
+    #[test]
+    fn sprt() {
+        let sprt = Sprt::default();
+        let (lower, upper) = sprt.bounds();
+        assert!((lower + 2.944).abs() < 0.001);
+        assert!((upper - 2.944).abs() < 0.001);
+        assert!((score_from_elo(elo_from_score(0.7)) - 0.7).abs() < 1e-12);
+
+        let mut stats = MatchStats {
+            wins: 60,
+            draws: 20,
+            losses: 20,
+            ..MatchStats::default()
+        };
+        assert!((sprt.llr(&stats).unwrap() - 0.883).abs() < 0.001);
+        assert_eq!(sprt.result(&stats), None);
+
+        // Ten times as many games of the same kind prove the engine
+        // stronger, and the same the other way around weaker.
+        stats.wins *= 10;
+        stats.draws *= 10;
+        stats.losses *= 10;
+        assert_eq!(sprt.result(&stats), Some(SprtResult::H1));
+        let weaker = MatchStats {
+            wins: stats.losses,
+            losses: stats.wins,
+            ..stats
+        };
+        assert_eq!(sprt.result(&weaker), Some(SprtResult::H0));
+
+        // Pairs of games decide when there are any.
+        let mut pairs = MatchStats::default();
+        for _ in 0..2000 {
+            pairs.add_pair(2);
+        }
+        for _ in 0..1500 {
+            pairs.add_pair(1);
+            pairs.add_pair(3);
+        }
+        assert!(sprt.pentanomial_llr(&pairs).unwrap() < 0.0);
+        assert_eq!(sprt.result(&pairs), Some(SprtResult::H0));
+    }
+}
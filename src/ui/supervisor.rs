@@ -0,0 +1,118 @@
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Child;
+use tokio::time::timeout;
+
+// Lines of an engine's stderr kept to explain a crash.
+const STDERR_LINES: usize = 20;
+
+// What can go wrong with an engine process. The UI shows these, and a
+// tournament logs them as the reason an engine lost its game.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EngineError {
+    Spawn(String),
+    // The engine didn't finish the uci or isready handshake.
+    Handshake(String),
+    // The engine sent something that isn't valid UCI.
+    Malformed(String),
+    Timeout(String),
+    // The process ended while it was still needed.
+    Exited { code: Option<i32>, stderr: String },
+    Io(String),
+}
+
+impl EngineError {
+    pub fn spawn(path: &Path, error: std::io::Error) -> Self {
+        EngineError::Spawn(format!("could not start {}: {}", path.display(), error))
+    }
+}
+
+impl std::fmt::Display for EngineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EngineError::Spawn(reason) => write!(f, "Engine failed to start: {}", reason),
+            EngineError::Handshake(reason) => write!(f, "Engine handshake failed: {}", reason),
+            EngineError::Malformed(line) => write!(f, "Engine sent invalid output: {}", line),
+            EngineError::Timeout(reason) => write!(f, "Engine timed out: {}", reason),
+            EngineError::Exited { code, stderr } => {
+                match code {
+                    Some(code) => write!(f, "Engine exited with code {}", code)?,
+                    None => write!(f, "Engine exited unexpectedly")?,
+                }
+                if !stderr.is_empty() {
+                    write!(f, "\n{}", stderr)?;
+                }
+                Ok(())
+            }
+            EngineError::Io(reason) => write!(f, "Engine communication failed: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
+
+impl From<std::io::Error> for EngineError {
+    fn from(error: std::io::Error) -> Self {
+        EngineError::Io(error.to_string())
+    }
+}
+
+// The last lines an engine wrote to stderr. They are read as they come in,
+// so a chatty engine can't block on a full pipe.
+#[derive(Debug, Clone, Default)]
+pub struct StderrLog(Arc<Mutex<VecDeque<String>>>);
+
+impl StderrLog {
+    pub fn capture(process: &mut Child) -> Self {
+        let log = StderrLog::default();
+        let Some(stderr) = process.stderr.take() else {
+            return log;
+        };
+
+        let lines = log.0.clone();
+        tokio::spawn(async move {
+            let mut reader = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = reader.next_line().await {
+                let Ok(mut lines) = lines.lock() else {
+                    break;
+                };
+                if lines.len() == STDERR_LINES {
+                    lines.pop_front();
+                }
+                lines.push_back(line);
+            }
+        });
+
+        log
+    }
+
+    pub fn text(&self) -> String {
+        self.0
+            .lock()
+            .map(|lines| lines.iter().cloned().collect::<Vec<_>>().join("\n"))
+            .unwrap_or_default()
+    }
+}
+
+// Most errors talking to an engine are caused by the process dying. If it
+// did, the error says so with its exit code and what it wrote to stderr.
+pub async fn diagnose(error: EngineError, process: &mut Child, stderr: &StderrLog) -> EngineError {
+    let status = match process.try_wait() {
+        Ok(Some(status)) => status,
+        _ => match timeout(Duration::from_millis(200), process.wait()).await {
+            Ok(Ok(status)) => status,
+            _ => return error,
+        },
+    };
+
+    // Give the stderr reader a moment to catch the last lines.
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    EngineError::Exited {
+        code: status.code(),
+        stderr: stderr.text(),
+    }
+}
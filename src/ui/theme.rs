@@ -0,0 +1,214 @@
+use std::path::PathBuf;
+
+use iced::advanced::svg;
+use iced::{Color, Theme};
+use serde::{Deserialize, Serialize};
+
+use super::config::BoardColors;
+use crate::board::defs::Pieces;
+use crate::defs::{Piece, Side, Sides};
+
+// The default piece set is part of the binary, so the pieces are found no
+// matter where the binary is moved to.
+const EMBEDDED_PIECES: [[&[u8]; 6]; 2] = [
+    [
+        include_bytes!("../../pieces/wK.svg"),
+        include_bytes!("../../pieces/wQ.svg"),
+        include_bytes!("../../pieces/wR.svg"),
+        include_bytes!("../../pieces/wB.svg"),
+        include_bytes!("../../pieces/wN.svg"),
+        include_bytes!("../../pieces/wP.svg"),
+    ],
+    [
+        include_bytes!("../../pieces/bK.svg"),
+        include_bytes!("../../pieces/bQ.svg"),
+        include_bytes!("../../pieces/bR.svg"),
+        include_bytes!("../../pieces/bB.svg"),
+        include_bytes!("../../pieces/bN.svg"),
+        include_bytes!("../../pieces/bP.svg"),
+    ],
+];
+
+// Directory next to the binary that holds extra piece sets, one
+// subdirectory per set with files named wK.svg, bQ.svg and so on.
+const PIECE_SET_DIRECTORY: &str = "pieces";
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum BoardStyle {
+    Brown,
+    Green,
+    Blue,
+    Grey,
+}
+
+impl BoardStyle {
+    pub const ALL: [BoardStyle; 4] = [
+        BoardStyle::Brown,
+        BoardStyle::Green,
+        BoardStyle::Blue,
+        BoardStyle::Grey,
+    ];
+
+    // The squares with highlights that stand out on them.
+    pub fn colors(&self) -> BoardColors {
+        match self {
+            BoardStyle::Brown => BoardColors::default(),
+            BoardStyle::Green => BoardColors {
+                light_square: Color::from_rgb(0.933, 0.933, 0.824),
+                dark_square: Color::from_rgb(0.463, 0.588, 0.337),
+                last_move: Color::from_rgba(0.96, 0.96, 0.41, 0.5),
+                selected: Color::from_rgba(0.08, 0.3, 0.55, 0.5),
+                check: Color::from_rgba(0.9, 0.1, 0.1, 0.7),
+                legal_move: Color::from_rgba(0.05, 0.2, 0.4, 0.5),
+            },
+            BoardStyle::Blue => BoardColors {
+                light_square: Color::from_rgb(0.871, 0.89, 0.902),
+                dark_square: Color::from_rgb(0.549, 0.635, 0.678),
+                last_move: Color::from_rgba(0.6, 0.85, 0.4, 0.5),
+                selected: Color::from_rgba(0.1, 0.4, 0.75, 0.5),
+                check: Color::from_rgba(0.85, 0.15, 0.2, 0.7),
+                legal_move: Color::from_rgba(0.1, 0.25, 0.5, 0.5),
+            },
+            BoardStyle::Grey => BoardColors {
+                light_square: Color::from_rgb(0.8, 0.8, 0.8),
+                dark_square: Color::from_rgb(0.55, 0.55, 0.55),
+                last_move: Color::from_rgba(0.95, 0.75, 0.25, 0.5),
+                selected: Color::from_rgba(0.25, 0.5, 0.8, 0.5),
+                check: Color::from_rgba(0.9, 0.1, 0.1, 0.7),
+                legal_move: Color::from_rgba(0.15, 0.15, 0.15, 0.45),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for BoardStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                BoardStyle::Brown => "Brown",
+                BoardStyle::Green => "Green",
+                BoardStyle::Blue => "Blue",
+                BoardStyle::Grey => "Grey",
+            }
+        )
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum AppTheme {
+    Light,
+    Dark,
+}
+
+impl AppTheme {
+    pub const ALL: [AppTheme; 2] = [AppTheme::Light, AppTheme::Dark];
+
+    pub fn theme(&self) -> Theme {
+        match self {
+            AppTheme::Light => Theme::Light,
+            AppTheme::Dark => Theme::Dark,
+        }
+    }
+}
+
+impl std::fmt::Display for AppTheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                AppTheme::Light => "Light",
+                AppTheme::Dark => "Dark",
+            }
+        )
+    }
+}
+
+// A set of piece images. The default set is embedded in the binary, other
+// sets are read from a directory.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct PieceSet {
+    pub name: String,
+    pub directory: Option<PathBuf>,
+}
+
+impl PieceSet {
+    pub fn embedded() -> Self {
+        Self {
+            name: "Default".to_string(),
+            directory: None,
+        }
+    }
+
+    // The embedded set followed by every set found in the piece set
+    // directory next to the binary.
+    pub fn available() -> Vec<PieceSet> {
+        let mut sets = vec![PieceSet::embedded()];
+
+        let directory = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(|dir| dir.join(PIECE_SET_DIRECTORY)));
+        if let Some(Ok(entries)) = directory.map(std::fs::read_dir) {
+            let mut found: Vec<PieceSet> = entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir())
+                .map(|path| PieceSet {
+                    name: path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                    directory: Some(path),
+                })
+                .collect();
+            found.sort_by(|a, b| a.name.cmp(&b.name));
+            sets.extend(found);
+        }
+
+        sets
+    }
+
+    pub fn svg(&self, side: Side, piece: Piece) -> svg::Handle {
+        let index = piece.min(Pieces::PAWN);
+
+        match &self.directory {
+            Some(directory) => {
+                let color = if side == Sides::WHITE { 'w' } else { 'b' };
+                let name = ['K', 'Q', 'R', 'B', 'N', 'P'][index];
+                svg::Handle::from_path(directory.join(format!("{}{}.svg", color, name)))
+            }
+            None => embedded_svg(side, piece),
+        }
+    }
+}
+
+pub fn embedded_svg(side: Side, piece: Piece) -> svg::Handle {
+    svg::Handle::from_memory(EMBEDDED_PIECES[side.min(Sides::BLACK)][piece.min(Pieces::PAWN)])
+}
+
+impl std::fmt::Display for PieceSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+// Everything about the look of the app the user can choose.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiTheme {
+    pub board: BoardStyle,
+    pub pieces: PieceSet,
+    pub app: AppTheme,
+}
+
+impl Default for UiTheme {
+    fn default() -> Self {
+        Self {
+            board: BoardStyle::Brown,
+            pieces: PieceSet::embedded(),
+            app: AppTheme::Dark,
+        }
+    }
+}
@@ -1,42 +1,565 @@
-use std::fs::File;
-use std::io::{self, Write};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug)]
+use super::openings::{self, Opening, OpeningOrder};
+use super::stats::{MatchStats, Sprt, SprtResult};
+
+// How the engines of a tournament are paired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TournamentFormat {
+    // Every engine plays every other engine.
+    #[default]
+    RoundRobin,
+    // The first engine plays all the others, who don't play each other.
+    Gauntlet,
+    // Every round engines play an opponent with a similar score that they
+    // haven't met yet.
+    Swiss,
+}
+
+impl TournamentFormat {
+    pub const ALL: [TournamentFormat; 3] = [
+        TournamentFormat::RoundRobin,
+        TournamentFormat::Gauntlet,
+        TournamentFormat::Swiss,
+    ];
+}
+
+impl std::fmt::Display for TournamentFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                TournamentFormat::RoundRobin => "Round robin",
+                TournamentFormat::Gauntlet => "Gauntlet",
+                TournamentFormat::Swiss => "Swiss",
+            }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+impl GameResult {
+    // The result as written in a PGN Result header.
+    pub fn from_pgn(result: &str) -> Option<Self> {
+        match result {
+            "1-0" => Some(GameResult::WhiteWins),
+            "0-1" => Some(GameResult::BlackWins),
+            "1/2-1/2" => Some(GameResult::Draw),
+            _ => None,
+        }
+    }
+
+    pub fn pgn(self) -> &'static str {
+        match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+        }
+    }
+}
+
+// A game of the schedule. The two games of a pairing share an opening and
+// are played with the colours reversed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pairing {
+    pub round: usize,
+    pub white: usize,
+    pub black: usize,
+    pub opening: usize,
+    pub result: Option<GameResult>,
+}
+
+impl Pairing {
+    // The points the engine got from the game in half points, if it played
+    // in it and the game is over.
+    pub fn half_points(&self, engine: usize) -> Option<u32> {
+        let result = self.result?;
+        if engine == self.white {
+            Some(match result {
+                GameResult::WhiteWins => 2,
+                GameResult::Draw => 1,
+                GameResult::BlackWins => 0,
+            })
+        } else if engine == self.black {
+            Some(match result {
+                GameResult::WhiteWins => 0,
+                GameResult::Draw => 1,
+                GameResult::BlackWins => 2,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Standing {
+    pub engine: usize,
+    pub half_points: u32,
+    pub games: usize,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+// The schedule of a tournament and the results so far. It is saved after
+// every game, so a tournament that was stopped can be resumed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Tournament {
-    games_to_play: usize,
-    pub games_played: usize,
-    engine1_wins: usize,
-    engine2_wins: usize,
-    draws: usize,
-    log_file: File,
+    pub format: TournamentFormat,
+    pub engines: Vec<String>,
+    pub rounds: usize,
+    pub games: Vec<Pairing>,
+    // Ends the tournament early once the first engine is proven stronger or
+    // not.
+    #[serde(default)]
+    pub sprt: Option<Sprt>,
+    // The openings the pairs of games start from, by their opening number.
+    // Without any they start from the start position.
+    #[serde(default)]
+    pub openings: Vec<Opening>,
 }
 
 impl Tournament {
-    pub fn new(games_to_play: usize, log_file_path: &str) -> io::Result<Self> {
-        let log_file = File::create(log_file_path)?;
-        Ok(Self {
-            games_to_play,
-            games_played: 0,
-            engine1_wins: 0,
-            engine2_wins: 0,
-            draws: 0,
-            log_file,
-        })
-    }
-
-    pub fn log_result(&mut self, result: &str) -> io::Result<()> {
-        self.games_played += 1;
-        writeln!(self.log_file, "Game {}: {}", self.games_played, result)?;
-        match result {
-            "Engine 1 wins" => self.engine1_wins += 1,
-            "Engine 2 wins" => self.engine2_wins += 1,
-            "Draw" => self.draws += 1,
-            _ => (),
+    pub fn new(format: TournamentFormat, engines: Vec<String>, rounds: usize) -> Self {
+        let mut tournament = Self {
+            format,
+            engines,
+            rounds: rounds.max(1),
+            games: vec![],
+            sprt: None,
+            openings: vec![],
+        };
+
+        let count = tournament.engines.len();
+        for round in 0..tournament.rounds {
+            match format {
+                TournamentFormat::RoundRobin => {
+                    for a in 0..count {
+                        for b in a + 1..count {
+                            tournament.add_pairing(round, a, b);
+                        }
+                    }
+                }
+                TournamentFormat::Gauntlet => {
+                    for b in 1..count {
+                        tournament.add_pairing(round, 0, b);
+                    }
+                }
+                // Swiss rounds depend on the results, so they are made when
+                // the round before is over.
+                TournamentFormat::Swiss => {
+                    tournament.add_swiss_round(0);
+                    break;
+                }
+            }
         }
-        Ok(())
+
+        tournament
+    }
+
+    // The index of the next game to play. A Swiss tournament gets its next
+    // round when the last one is over. None when the tournament is over,
+    // which can be early when there is an SPRT.
+    pub fn next_game(&mut self) -> Option<usize> {
+        self.next_game_besides(&[])
+    }
+
+    // The next game to play while the given games are still being played.
+    // A Swiss round is only paired once all games of the last one are over,
+    // so until then there may be no game to start.
+    pub fn next_game_besides(&mut self, playing: &[usize]) -> Option<usize> {
+        if self.sprt_result().is_some() {
+            return None;
+        }
+        let unplayed = |(index, game): (usize, &Pairing)| {
+            (game.result.is_none() && !playing.contains(&index)).then_some(index)
+        };
+        if let Some(index) = self.games.iter().enumerate().find_map(unplayed) {
+            return Some(index);
+        }
+        if !playing.is_empty() {
+            return None;
+        }
+
+        let round = self.games.last().map_or(0, |game| game.round + 1);
+        if self.format != TournamentFormat::Swiss || round >= self.rounds {
+            return None;
+        }
+        self.add_swiss_round(round);
+        self.games.iter().position(|game| game.result.is_none())
+    }
+
+    // Take the openings for the pairs of games from the suite. Only those are
+    // kept, so a big suite doesn't end up in the saved tournament.
+    pub fn set_openings(&mut self, suite: Vec<Opening>, order: OpeningOrder, seed: u64) {
+        let pairs = match self.format {
+            TournamentFormat::Swiss => self.rounds * (self.engines.len() / 2),
+            _ => self.games.len() / 2,
+        };
+        self.openings = openings::choose(suite, pairs, order, seed);
+    }
+
+    pub fn opening(&self, game: usize) -> Option<&Opening> {
+        let opening = self.games.get(game)?.opening;
+        self.openings.get(opening % self.openings.len().max(1))
     }
 
-    pub fn tournament_over(&self) -> bool {
-        self.games_played >= self.games_to_play
+    pub fn record(&mut self, game: usize, result: GameResult) {
+        if let Some(game) = self.games.get_mut(game) {
+            game.result = Some(result);
+        }
+    }
+
+    pub fn games_played(&self) -> usize {
+        self.games
+            .iter()
+            .filter(|game| game.result.is_some())
+            .count()
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.clone().next_game().is_none()
+    }
+
+    // Every engine with its score, the best first.
+    pub fn standings(&self) -> Vec<Standing> {
+        let mut standings: Vec<Standing> = (0..self.engines.len())
+            .map(|engine| Standing {
+                engine,
+                ..Standing::default()
+            })
+            .collect();
+
+        for game in &self.games {
+            for engine in [game.white, game.black] {
+                let Some(points) = game.half_points(engine) else {
+                    continue;
+                };
+                let standing = &mut standings[engine];
+                standing.half_points += points;
+                standing.games += 1;
+                match points {
+                    2 => standing.wins += 1,
+                    1 => standing.draws += 1,
+                    _ => standing.losses += 1,
+                }
+            }
+        }
+
+        standings.sort_by(|a, b| {
+            b.half_points
+                .cmp(&a.half_points)
+                .then(a.engine.cmp(&b.engine))
+        });
+        standings
+    }
+
+    // The half points the engine scored against the opponent, and the number
+    // of games they played.
+    pub fn score_against(&self, engine: usize, opponent: usize) -> (u32, usize) {
+        self.games
+            .iter()
+            .filter(|game| {
+                (game.white, game.black) == (engine, opponent)
+                    || (game.white, game.black) == (opponent, engine)
+            })
+            .filter_map(|game| game.half_points(engine))
+            .fold((0, 0), |(points, games), p| (points + p, games + 1))
+    }
+
+    // The results of the engine against the opponent, or against all the
+    // engines it played when there is no opponent.
+    pub fn match_stats(&self, engine: usize, opponent: Option<usize>) -> MatchStats {
+        let plays = |game: &Pairing| {
+            let players = [game.white, game.black];
+            players.contains(&engine) && opponent.is_none_or(|o| players.contains(&o))
+        };
+
+        // The games are scheduled in pairs with the same opening.
+        let mut stats = MatchStats::default();
+        for pair in self.games.chunks(2) {
+            let points: Vec<u32> = pair
+                .iter()
+                .filter(|game| plays(game))
+                .filter_map(|game| game.half_points(engine))
+                .collect();
+            for &half_points in &points {
+                stats.add_game(half_points);
+            }
+            if points.len() == 2 {
+                stats.add_pair(points[0] + points[1]);
+            }
+        }
+
+        stats
+    }
+
+    // The SPRT result of the first engine against the others, once it has
+    // crossed a bound.
+    pub fn sprt_result(&self) -> Option<SprtResult> {
+        self.sprt?.result(&self.match_stats(0, None))
+    }
+
+    // The running log likelihood ratio and the bounds.
+    pub fn sprt_summary(&self) -> Option<String> {
+        Some(self.sprt?.summary(&self.match_stats(0, None)))
+    }
+
+    // The first engine is the one being tested, as in a gauntlet. Its results
+    // against every opponent, and against all of them together when there
+    // are more.
+    pub fn test_results(&self) -> Vec<(String, MatchStats)> {
+        let Some(tested) = self.engines.first() else {
+            return vec![];
+        };
+
+        let mut results: Vec<(String, MatchStats)> = (1..self.engines.len())
+            .map(|opponent| {
+                (
+                    format!("{} vs {}", tested, self.engines[opponent]),
+                    self.match_stats(0, Some(opponent)),
+                )
+            })
+            .filter(|(_, stats)| stats.games() > 0)
+            .collect();
+        if self.engines.len() > 2 {
+            results.push((format!("{} vs all", tested), self.match_stats(0, None)));
+        }
+
+        results
+    }
+
+    // The standings with the score of every engine against every other, as
+    // text for the log.
+    pub fn crosstable(&self) -> String {
+        let standings = self.standings();
+        let width = self
+            .engines
+            .iter()
+            .map(|name| name.len())
+            .max()
+            .unwrap_or(0)
+            .max(6);
+
+        let mut table = format!(
+            "{:>2}  {:<width$}  {:>6}  {:>5}",
+            "#", "Engine", "Points", "Games"
+        );
+        for rank in 1..=standings.len() {
+            table.push_str(&format!("  {:>5}", rank));
+        }
+        for (rank, standing) in standings.iter().enumerate() {
+            table.push_str(&format!(
+                "\n{:>2}  {:<width$}  {:>6}  {:>5}",
+                rank + 1,
+                self.engines[standing.engine],
+                format_points(standing.half_points),
+                standing.games
+            ));
+            for opponent in &standings {
+                let cell = match self.score_against(standing.engine, opponent.engine) {
+                    _ if opponent.engine == standing.engine => "*".to_string(),
+                    (_, 0) => "-".to_string(),
+                    (points, _) => format_points(points),
+                };
+                table.push_str(&format!("  {:>5}", cell));
+            }
+        }
+
+        table
+    }
+
+    fn add_pairing(&mut self, round: usize, a: usize, b: usize) {
+        // The engine that starts with white alternates from round to round.
+        let (first, second) = if round.is_multiple_of(2) {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        let opening = self.games.len() / 2;
+
+        for (white, black) in [(first, second), (second, first)] {
+            self.games.push(Pairing {
+                round,
+                white,
+                black,
+                opening,
+                result: None,
+            });
+        }
+    }
+
+    // Pair the engines from the top of the standings down, each with the
+    // next engine it hasn't played yet. With an odd number of engines the
+    // last one sits the round out.
+    fn add_swiss_round(&mut self, round: usize) {
+        let mut unpaired: Vec<usize> = self
+            .standings()
+            .iter()
+            .map(|standing| standing.engine)
+            .collect();
+
+        while unpaired.len() >= 2 {
+            let engine = unpaired.remove(0);
+            let opponent = unpaired
+                .iter()
+                .position(|&opponent| self.score_against(engine, opponent).1 == 0)
+                .unwrap_or(0);
+            let opponent = unpaired.remove(opponent);
+            self.add_pairing(round, engine, opponent);
+        }
+    }
+}
+
+// Half points as points, like 2.5.
+pub fn format_points(half_points: u32) -> String {
+    if half_points.is_multiple_of(2) {
+        (half_points / 2).to_string()
+    } else {
+        format!("{}.5", half_points / 2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engines(count: usize) -> Vec<String> {
+        (1..=count).map(|n| format!("Engine {}", n)).collect()
+    }
+
+    #[test]
+    fn schedules() {
+        let round_robin = Tournament::new(TournamentFormat::RoundRobin, engines(4), 2);
+        // Six pairings of two games in each round.
+        assert_eq!(round_robin.games.len(), 24);
+        for engine in 0..4 {
+            let white = round_robin
+                .games
+                .iter()
+                .filter(|game| game.white == engine)
+                .count();
+            assert_eq!(white, 6);
+        }
+        // Both games of a pairing start from the same opening, with the
+        // colours reversed.
+        let (first, second) = (&round_robin.games[0], &round_robin.games[1]);
+        assert_eq!(first.opening, second.opening);
+        assert_eq!((first.white, first.black), (second.black, second.white));
+        // The next round starts with the other colour.
+        assert_eq!(round_robin.games[12].white, 1);
+
+        // A suite shorter than the schedule is played again from the start.
+        let mut with_openings = round_robin.clone();
+        let suite = (0..5)
+            .map(|n| Opening {
+                name: n.to_string(),
+                fen: "8/8/8/4k3/8/8/8/4K3 w - - 0 1".to_string(),
+                moves: vec![],
+            })
+            .collect();
+        with_openings.set_openings(suite, OpeningOrder::Sequential, 0);
+        assert_eq!(with_openings.opening(0), with_openings.opening(1));
+        assert_eq!(with_openings.opening(11).unwrap().name, "0");
+
+        let gauntlet = Tournament::new(TournamentFormat::Gauntlet, engines(4), 1);
+        assert_eq!(gauntlet.games.len(), 6);
+        assert!(gauntlet
+            .games
+            .iter()
+            .all(|game| game.white == 0 || game.black == 0));
+    }
+
+    #[test]
+    fn swiss_rounds_avoid_rematches() {
+        let mut swiss = Tournament::new(TournamentFormat::Swiss, engines(4), 3);
+        assert_eq!(swiss.games.len(), 4);
+        // Games played side by side are skipped, and the next round waits
+        // for the last one to be over.
+        assert_eq!(swiss.next_game_besides(&[0, 1, 2]), Some(3));
+        assert_eq!(swiss.next_game_besides(&[0, 1, 2, 3]), None);
+        assert_eq!(swiss.games.len(), 4);
+
+        // Engine 1 beats engine 2 twice, engine 3 and 4 draw twice.
+        for (game, result) in [
+            GameResult::WhiteWins,
+            GameResult::BlackWins,
+            GameResult::Draw,
+            GameResult::Draw,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            assert_eq!(swiss.next_game(), Some(game));
+            swiss.record(game, result);
+        }
+
+        // The leader meets the next engine it hasn't played.
+        assert_eq!(swiss.next_game(), Some(4));
+        let game = &swiss.games[4];
+        assert_eq!((game.round, game.white.min(game.black)), (1, 0));
+        assert_eq!(game.white.max(game.black), 2);
+        assert!(!swiss.is_over());
+    }
+
+    #[test]
+    fn standings_and_resuming() {
+        let mut tournament = Tournament::new(TournamentFormat::RoundRobin, engines(3), 1);
+        tournament.record(0, GameResult::WhiteWins);
+        tournament.record(1, GameResult::Draw);
+        tournament.record(2, GameResult::BlackWins);
+
+        let standings = tournament.standings();
+        assert_eq!(standings[0].engine, 0);
+        assert_eq!(format_points(standings[0].half_points), "1.5");
+        assert_eq!((standings[0].wins, standings[0].draws), (1, 1));
+        assert_eq!(tournament.score_against(2, 0), (2, 1));
+        assert!(tournament.crosstable().contains("Engine 1"));
+
+        // Engine 1 won and drew the pair of games against engine 2.
+        let stats = tournament.match_stats(0, Some(1));
+        assert_eq!((stats.wins, stats.draws, stats.losses), (1, 1, 0));
+        assert_eq!(stats.pentanomial, [0, 0, 0, 1, 0]);
+        assert_eq!(tournament.match_stats(0, None).games(), 3);
+        assert_eq!(tournament.test_results().len(), 3);
+
+        let saved = serde_json::to_string(&tournament).unwrap();
+        let mut resumed: Tournament = serde_json::from_str(&saved).unwrap();
+        assert_eq!(resumed, tournament);
+        assert_eq!(resumed.next_game(), Some(3));
+        assert_eq!(resumed.games_played(), 3);
+    }
+
+    #[test]
+    fn sprt_ends_a_match() {
+        let mut tournament = Tournament::new(TournamentFormat::RoundRobin, engines(2), 1000);
+        tournament.sprt = Some(Sprt::default());
+
+        // The first engine wins two of every three pairs of games 1.5 to
+        // 0.5, and draws the others.
+        let mut played = 0;
+        while let Some(game) = tournament.next_game() {
+            let result = if game % 2 == 0 || game % 6 == 5 {
+                GameResult::Draw
+            } else if tournament.games[game].white == 0 {
+                GameResult::WhiteWins
+            } else {
+                GameResult::BlackWins
+            };
+            tournament.record(game, result);
+            played += 1;
+        }
+
+        assert_eq!(tournament.sprt_result(), Some(SprtResult::H1));
+        assert!(played < tournament.games.len());
+        assert!(tournament.is_over());
     }
 }
@@ -0,0 +1,412 @@
+use iced::widget::{
+    column, row, Button, Checkbox, Column, Container, PickList, Scrollable, Text, TextInput,
+};
+use iced::{alignment, Alignment, Element, Length, Theme};
+use iced_aw::TabLabel;
+
+use super::config::TournamentConfig;
+use super::openings::OpeningOrder;
+use super::settings::parse_number;
+use super::tournament::{format_points, Tournament, TournamentFormat};
+use super::ui::{Message, Tab};
+
+#[derive(Clone, Debug)]
+pub enum TournamentMessage {
+    SelectFormat(TournamentFormat),
+    ToggleEngine(String, bool),
+    SetRounds(String),
+    ToggleSprt(bool),
+    // Elo0, elo1, alpha or beta, by their place in SPRT_FIELDS.
+    SetSprt(usize, String),
+    SetOpeningFile(String),
+    SetOpeningPlies(String),
+    SelectOpeningOrder(OpeningOrder),
+    SetOpeningSeed(String),
+    ToggleResign(bool),
+    ToggleDrawAdjudication(bool),
+    // A number of the adjudication rules, by its place in
+    // ADJUDICATION_FIELDS.
+    SetAdjudication(usize, String),
+}
+
+const SPRT_FIELDS: [&str; 4] = ["elo0", "elo1", "alpha", "beta"];
+const ADJUDICATION_FIELDS: [&str; 6] = [
+    "resign moves",
+    "resign cp",
+    "draw from move",
+    "draw moves",
+    "draw cp",
+    "max moves",
+];
+
+// Sets up a tournament between the registered engines, and shows the
+// standings while it runs.
+pub struct TournamentTab {
+    pub config: TournamentConfig,
+    // Names of the registered engines that can take part.
+    pub available: Vec<String>,
+    pub running: Option<Tournament>,
+    // The game of the schedule that is on the board.
+    pub current_game: Option<usize>,
+    // Whether a tournament that was stopped can be resumed.
+    pub resumable: bool,
+    pub status: String,
+    // The SPRT fields as typed, since "0." isn't a number yet.
+    sprt_text: [String; 4],
+}
+
+impl TournamentTab {
+    pub fn new(config: TournamentConfig, available: Vec<String>, resumable: bool) -> Self {
+        let sprt = config.sprt;
+        Self {
+            sprt_text: [sprt.elo0, sprt.elo1, sprt.alpha, sprt.beta].map(|value| value.to_string()),
+            config,
+            available,
+            running: None,
+            current_game: None,
+            resumable,
+            status: String::new(),
+        }
+    }
+
+    pub fn update(&mut self, message: TournamentMessage) {
+        match message {
+            TournamentMessage::SelectFormat(format) => self.config.format = format,
+            TournamentMessage::ToggleEngine(name, true) => {
+                if !self.config.engines.contains(&name) {
+                    self.config.engines.push(name);
+                }
+            }
+            TournamentMessage::ToggleEngine(name, false) => {
+                self.config.engines.retain(|engine| engine != &name);
+            }
+            TournamentMessage::SetRounds(value) => {
+                self.config.rounds = (parse_number(&value) as usize).max(1);
+            }
+            TournamentMessage::SetOpeningFile(path) => self.config.opening_file = path,
+            TournamentMessage::SetOpeningPlies(value) => {
+                self.config.opening_plies = parse_number(&value) as usize;
+            }
+            TournamentMessage::SelectOpeningOrder(order) => self.config.opening_order = order,
+            TournamentMessage::SetOpeningSeed(value) => {
+                self.config.opening_seed = parse_number(&value);
+            }
+            TournamentMessage::ToggleResign(enabled) => self.config.adjudication.resign = enabled,
+            TournamentMessage::ToggleDrawAdjudication(enabled) => {
+                self.config.adjudication.draw = enabled;
+            }
+            TournamentMessage::SetAdjudication(field, value) => {
+                if let Some(number) = self.adjudication_field(field) {
+                    *number = parse_number(&value) as u32;
+                }
+            }
+            TournamentMessage::ToggleSprt(enabled) => self.config.sprt_enabled = enabled,
+            TournamentMessage::SetSprt(field, value) => {
+                // Error rates have to be a chance, but not a certainty.
+                let sprt = &mut self.config.sprt;
+                match (field, value.trim().parse::<f64>()) {
+                    (0, Ok(elo)) => sprt.elo0 = elo,
+                    (1, Ok(elo)) => sprt.elo1 = elo,
+                    (2, Ok(alpha)) if alpha > 0.0 && alpha < 1.0 => sprt.alpha = alpha,
+                    (3, Ok(beta)) if beta > 0.0 && beta < 1.0 => sprt.beta = beta,
+                    _ => (),
+                }
+                if let Some(text) = self.sprt_text.get_mut(field) {
+                    *text = value;
+                }
+            }
+        }
+    }
+
+    // The chosen engines that are still registered, in the order they were
+    // registered. In a gauntlet the first one plays all the others.
+    pub fn engines(&self) -> Vec<String> {
+        self.available
+            .iter()
+            .filter(|name| self.config.engines.contains(name))
+            .cloned()
+            .collect()
+    }
+
+    // A table of the engines with their scores, the leader first.
+    pub fn standings(&self) -> Option<Element<'_, Message, iced::Renderer<Theme>>> {
+        let tournament = self.running.as_ref()?;
+
+        let mut table = Column::new().spacing(5).push(
+            row![
+                Text::new("#").width(25),
+                Text::new("Engine").width(Length::Fill),
+                Text::new("Points").width(55),
+                Text::new("Games").width(50),
+                Text::new("+ / = / -").width(80),
+            ]
+            .spacing(5),
+        );
+        for (rank, standing) in tournament.standings().iter().enumerate() {
+            table = table.push(
+                row![
+                    Text::new((rank + 1).to_string()).width(25),
+                    Text::new(&tournament.engines[standing.engine]).width(Length::Fill),
+                    Text::new(format_points(standing.half_points)).width(55),
+                    Text::new(standing.games.to_string()).width(50),
+                    Text::new(format!(
+                        "{} / {} / {}",
+                        standing.wins, standing.draws, standing.losses
+                    ))
+                    .width(80),
+                ]
+                .spacing(5),
+            );
+        }
+
+        let mut results = Column::new().spacing(5);
+        for (title, stats) in tournament.test_results() {
+            results = results
+                .push(Text::new(title).size(14))
+                .push(Text::new(stats.summary()).size(12));
+        }
+        if let Some(sprt) = tournament.sprt_summary() {
+            results = results.push(Text::new(sprt).size(12));
+        }
+
+        Some(
+            column![Text::new(&self.status).size(14), table, results]
+                .spacing(10)
+                .padding(10)
+                .into(),
+        )
+    }
+}
+
+impl TournamentTab {
+    fn adjudication_field(&mut self, field: usize) -> Option<&mut u32> {
+        let adjudication = &mut self.config.adjudication;
+        match field {
+            0 => Some(&mut adjudication.resign_moves),
+            1 => Some(&mut adjudication.resign_score),
+            2 => Some(&mut adjudication.draw_from_move),
+            3 => Some(&mut adjudication.draw_moves),
+            4 => Some(&mut adjudication.draw_score),
+            5 => Some(&mut adjudication.max_moves),
+            _ => None,
+        }
+    }
+
+    // The numbers of a rule are only shown while it is on. The move limit
+    // is off at 0.
+    fn adjudication_view(&self) -> Element<'_, TournamentMessage, iced::Renderer<Theme>> {
+        let adjudication = &self.config.adjudication;
+        let values = [
+            adjudication.resign_moves,
+            adjudication.resign_score,
+            adjudication.draw_from_move,
+            adjudication.draw_moves,
+            adjudication.draw_score,
+            adjudication.max_moves,
+        ];
+        let field = |field: usize| {
+            TextInput::new(ADJUDICATION_FIELDS[field], &values[field].to_string())
+                .on_input(move |value| TournamentMessage::SetAdjudication(field, value))
+                .width(60)
+        };
+
+        let mut resign = row![Checkbox::new(
+            "Resign",
+            adjudication.resign,
+            TournamentMessage::ToggleResign
+        )
+        .size(20)]
+        .spacing(10)
+        .align_items(Alignment::Center);
+        if adjudication.resign {
+            resign = resign
+                .push(Text::new("moves: "))
+                .push(field(0))
+                .push(Text::new("cp: "))
+                .push(field(1));
+        }
+
+        let mut draw = row![Checkbox::new(
+            "Draw",
+            adjudication.draw,
+            TournamentMessage::ToggleDrawAdjudication
+        )
+        .size(20)]
+        .spacing(10)
+        .align_items(Alignment::Center);
+        if adjudication.draw {
+            draw = draw
+                .push(Text::new("from move: "))
+                .push(field(2))
+                .push(Text::new("moves: "))
+                .push(field(3))
+                .push(Text::new("cp: "))
+                .push(field(4));
+        }
+
+        column![
+            resign,
+            draw,
+            row![Text::new("Max moves: "), field(5)]
+                .spacing(10)
+                .align_items(Alignment::Center),
+        ]
+        .spacing(10)
+        .into()
+    }
+
+    // The plies only matter for PGN suites, and the seed for random order.
+    fn openings_view(&self) -> Element<'_, TournamentMessage, iced::Renderer<Theme>> {
+        let mut view = column![row![
+            Text::new("Openings: "),
+            TextInput::new("EPD or PGN file", &self.config.opening_file)
+                .on_input(TournamentMessage::SetOpeningFile),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center)]
+        .spacing(10);
+
+        if !self.config.opening_file.trim().is_empty() {
+            let mut options = row![
+                Text::new("Plies: "),
+                TextInput::new("8", &self.config.opening_plies.to_string())
+                    .on_input(TournamentMessage::SetOpeningPlies)
+                    .width(50),
+                PickList::new(
+                    &OpeningOrder::ALL[..],
+                    Some(self.config.opening_order),
+                    TournamentMessage::SelectOpeningOrder
+                ),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center);
+            if self.config.opening_order == OpeningOrder::Random {
+                options = options.push(Text::new("Seed: ")).push(
+                    TextInput::new("1", &self.config.opening_seed.to_string())
+                        .on_input(TournamentMessage::SetOpeningSeed)
+                        .width(80),
+                );
+            }
+            view = view.push(options);
+        }
+
+        view.into()
+    }
+
+    // The SPRT tests the first engine against the others.
+    fn sprt_view(&self) -> Element<'_, TournamentMessage, iced::Renderer<Theme>> {
+        let mut fields = row![Checkbox::new(
+            "SPRT",
+            self.config.sprt_enabled,
+            TournamentMessage::ToggleSprt
+        )
+        .size(20)]
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+        if self.config.sprt_enabled {
+            for (field, (name, text)) in SPRT_FIELDS.iter().zip(&self.sprt_text).enumerate() {
+                fields = fields.push(
+                    TextInput::new(name, text)
+                        .on_input(move |value| TournamentMessage::SetSprt(field, value))
+                        .width(50),
+                );
+            }
+        }
+
+        fields.into()
+    }
+}
+
+impl Tab for TournamentTab {
+    type Message = Message;
+
+    fn title(&self) -> String {
+        "Tournament".to_string()
+    }
+
+    fn tab_label(&self) -> TabLabel {
+        TabLabel::Text(self.title())
+    }
+
+    fn content(&self) -> Element<'_, Message, iced::Renderer<Theme>> {
+        if let Some(standings) = self.standings() {
+            let content = column![
+                standings,
+                Button::new(Text::new("Stop")).on_press(Message::StopTournament),
+            ]
+            .spacing(15);
+            return Container::new(Scrollable::new(content))
+                .height(Length::Fill)
+                .width(Length::Fill)
+                .into();
+        }
+
+        let mut engines = Column::new().spacing(5);
+        for name in &self.available {
+            let name = name.clone();
+            engines = engines.push(
+                Checkbox::new(
+                    name.clone(),
+                    self.config.engines.contains(&name),
+                    move |checked| TournamentMessage::ToggleEngine(name.clone(), checked),
+                )
+                .size(20),
+            );
+        }
+        if self.available.is_empty() {
+            engines = engines.push(Text::new("Add engines in the engines tab").size(14));
+        }
+
+        let form: Element<TournamentMessage, iced::Renderer<Theme>> = column![
+            row![
+                Text::new("Format: "),
+                PickList::new(
+                    &TournamentFormat::ALL[..],
+                    Some(self.config.format),
+                    TournamentMessage::SelectFormat
+                )
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+            row![
+                Text::new("Rounds: "),
+                TextInput::new("2", &self.config.rounds.to_string())
+                    .on_input(TournamentMessage::SetRounds)
+                    .width(60),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+            self.sprt_view(),
+            self.adjudication_view(),
+            self.openings_view(),
+            engines,
+        ]
+        .spacing(15)
+        .into();
+
+        // A tournament needs at least two engines.
+        let mut start = Button::new(Text::new("Start"));
+        if self.engines().len() >= 2 {
+            start = start.on_press(Message::StartTournament);
+        }
+        let mut resume = Button::new(Text::new("Resume"));
+        if self.resumable {
+            resume = resume.on_press(Message::ResumeTournament);
+        }
+
+        let content = column![
+            form.map(Message::Tournament),
+            row![start, resume].spacing(10),
+            Text::new(&self.status).size(14),
+        ]
+        .spacing(15)
+        .padding(10);
+
+        Container::new(Scrollable::new(content))
+            .align_x(alignment::Horizontal::Center)
+            .height(Length::Fill)
+            .width(Length::Fill)
+            .into()
+    }
+}
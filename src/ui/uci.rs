@@ -0,0 +1,410 @@
+use serde::{Deserialize, Serialize};
+
+// The kinds of options an engine can report, see the `option` command in the
+// UCI protocol.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum UiElement {
+    Spin { min: i64, max: i64 },
+    Check,
+    Combo(Vec<String>),
+    Button,
+    String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EngineOption {
+    pub name: String,
+    pub ui_element: UiElement,
+    pub default: Option<String>,
+}
+
+// Keywords that separate the fields of an option line. Names and values can
+// contain spaces, so a field runs until the next keyword.
+const OPTION_KEYWORDS: [&str; 6] = ["name", "type", "default", "min", "max", "var"];
+
+impl EngineOption {
+    // Parse a line like
+    // "option name Hash type spin default 16 min 1 max 33554432".
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut words = line.split_whitespace();
+        if words.next() != Some("option") {
+            return None;
+        }
+
+        let mut fields: Vec<(&str, Vec<&str>)> = vec![];
+        for word in words {
+            // "name" and "var" values may contain keywords, but never start
+            // with one, so a keyword only starts a field after a value.
+            match fields.last_mut() {
+                Some((key, value))
+                    if !OPTION_KEYWORDS.contains(&word)
+                        || value.is_empty()
+                        || (*key == "name" && word != "type") =>
+                {
+                    value.push(word)
+                }
+                _ => fields.push((word, vec![])),
+            }
+        }
+
+        let field = |key: &str| {
+            fields
+                .iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, value)| value.join(" "))
+        };
+
+        let name = field("name").filter(|name| !name.is_empty())?;
+        let default = field("default").map(|d| if d == "<empty>" { String::new() } else { d });
+
+        let ui_element = match field("type")?.as_str() {
+            "spin" => UiElement::Spin {
+                min: field("min")
+                    .and_then(|m| m.parse().ok())
+                    .unwrap_or(i64::MIN),
+                max: field("max")
+                    .and_then(|m| m.parse().ok())
+                    .unwrap_or(i64::MAX),
+            },
+            "check" => UiElement::Check,
+            "combo" => UiElement::Combo(
+                fields
+                    .iter()
+                    .filter(|(key, _)| *key == "var")
+                    .map(|(_, value)| value.join(" "))
+                    .collect(),
+            ),
+            "button" => UiElement::Button,
+            "string" => UiElement::String,
+            _ => return None,
+        };
+
+        Some(Self {
+            name,
+            ui_element,
+            default,
+        })
+    }
+
+    // The setoption command for the value, or None if the value isn't valid
+    // for this option. Spin values are clamped to the allowed range.
+    pub fn setoption(&self, value: &str) -> Option<String> {
+        let value = match &self.ui_element {
+            UiElement::Spin { min, max } => value
+                .trim()
+                .parse::<i64>()
+                .ok()?
+                .clamp(*min, *max)
+                .to_string(),
+            UiElement::Check => match value {
+                "true" | "false" => value.to_string(),
+                _ => return None,
+            },
+            UiElement::Combo(vars) => vars.iter().find(|v| v.eq_ignore_ascii_case(value))?.clone(),
+            UiElement::Button => return Some(format!("setoption name {}", self.name)),
+            UiElement::String => {
+                if value.is_empty() {
+                    "<empty>".to_string()
+                } else {
+                    value.to_string()
+                }
+            }
+        };
+
+        Some(format!("setoption name {} value {}", self.name, value))
+    }
+}
+
+// A score from the point of view of the side to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Score {
+    Cp(i32),
+    // Moves to mate, negative when the side to move gets mated.
+    Mate(i32),
+}
+
+impl Score {
+    // Chance of winning for the side to move in percent, from a curve fitted
+    // on the results of rated games. A mate pins it to either end.
+    pub fn win_percent(self) -> f32 {
+        match self {
+            Score::Cp(cp) => 50.0 + 50.0 * (2.0 / (1.0 + (-0.00368208 * cp as f32).exp()) - 1.0),
+            Score::Mate(moves) if moves > 0 => 100.0,
+            Score::Mate(_) => 0.0,
+        }
+    }
+
+    pub fn white_win_percent(self, white_to_move: bool) -> f32 {
+        if white_to_move {
+            self.win_percent()
+        } else {
+            100.0 - self.win_percent()
+        }
+    }
+
+    // The score from white's point of view, like "+0.35", "M3" or "-M3".
+    pub fn white_text(self, white_to_move: bool) -> String {
+        match self {
+            Score::Cp(cp) => {
+                let cp = if white_to_move { cp } else { -cp };
+                format!("{:+.2}", cp as f32 / 100.0)
+            }
+            // Mate 0 means the side to move is mated.
+            Score::Mate(moves) => {
+                let white_mates = (moves > 0) == white_to_move;
+                format!("{}M{}", if white_mates { "" } else { "-" }, moves.abs())
+            }
+        }
+    }
+}
+
+// Whether the score is exact or only a bound, when the engine reports a
+// score before the search of a move finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Bound {
+    #[default]
+    Exact,
+    Lower,
+    Upper,
+}
+
+// One `info` line of an engine. Everything is optional, engines send the
+// fields they like in any order.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EngineInfo {
+    pub depth: Option<u32>,
+    pub seldepth: Option<u32>,
+    pub multipv: Option<u32>,
+    pub score: Option<Score>,
+    pub bound: Bound,
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+    pub hashfull: Option<u32>,
+    pub tbhits: Option<u64>,
+    pub time: Option<u64>,
+    pub pv: Vec<String>,
+    pub currmove: Option<String>,
+    pub currmovenumber: Option<u32>,
+    pub string: Option<String>,
+}
+
+impl EngineInfo {
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut words = line.split_whitespace().peekable();
+        if words.next() != Some("info") {
+            return None;
+        }
+
+        let mut info = EngineInfo::default();
+        while let Some(word) = words.next() {
+            match word {
+                "depth" => info.depth = words.next().and_then(|w| w.parse().ok()),
+                "seldepth" => info.seldepth = words.next().and_then(|w| w.parse().ok()),
+                "multipv" => info.multipv = words.next().and_then(|w| w.parse().ok()),
+                "nodes" => info.nodes = words.next().and_then(|w| w.parse().ok()),
+                "nps" => info.nps = words.next().and_then(|w| w.parse().ok()),
+                "hashfull" => info.hashfull = words.next().and_then(|w| w.parse().ok()),
+                "tbhits" => info.tbhits = words.next().and_then(|w| w.parse().ok()),
+                "time" => info.time = words.next().and_then(|w| w.parse().ok()),
+                "currmove" => info.currmove = words.next().map(str::to_string),
+                "currmovenumber" => info.currmovenumber = words.next().and_then(|w| w.parse().ok()),
+                "score" => {
+                    let kind = words.next();
+                    let value = words.next().and_then(|w| w.parse().ok());
+                    info.score = match (kind, value) {
+                        (Some("cp"), Some(cp)) => Some(Score::Cp(cp)),
+                        (Some("mate"), Some(mate)) => Some(Score::Mate(mate)),
+                        _ => None,
+                    };
+                    match words.peek() {
+                        Some(&"lowerbound") => info.bound = Bound::Lower,
+                        Some(&"upperbound") => info.bound = Bound::Upper,
+                        _ => continue,
+                    }
+                    words.next();
+                }
+                // The pv and string run until the end of the line.
+                "pv" => info.pv = words.by_ref().map(str::to_string).collect(),
+                "string" => info.string = Some(words.by_ref().collect::<Vec<_>>().join(" ")),
+                _ => (),
+            }
+        }
+
+        Some(info)
+    }
+}
+
+// The longest a search started with the go command can take in
+// milliseconds: its movetime, or all the time on the clock of the side to
+// move. None for searches that aren't limited by time.
+pub fn search_time_limit(go: &str, white_to_move: bool) -> Option<u64> {
+    let clock = if white_to_move { "wtime" } else { "btime" };
+    let mut words = go.split_whitespace();
+    let mut limit = None;
+
+    while let Some(word) = words.next() {
+        match word {
+            "infinite" | "ponder" => return None,
+            "movetime" => return words.next().and_then(|w| w.parse().ok()),
+            w if w == clock => limit = words.next().and_then(|w| w.parse().ok()),
+            _ => (),
+        }
+    }
+
+    limit
+}
+
+// The position line after some more moves.
+pub fn with_moves(position: &str, moves: &[&str]) -> String {
+    let mut line = position.trim().to_string();
+    if !line.contains(" moves") {
+        line.push_str(" moves");
+    }
+    for m in moves {
+        line.push(' ');
+        line.push_str(m);
+    }
+
+    line
+}
+
+// The go command for pondering after the engine answered a search. Its own
+// clock is down by the time it just thought, which the opponent's clock
+// doesn't know about yet.
+pub fn ponder_command(go: &str, white_moved: bool, thought_ms: u64) -> String {
+    let clock = if white_moved { "wtime" } else { "btime" };
+    let mut words = go.split_whitespace().skip(1);
+    let mut command = String::from("go ponder");
+
+    while let Some(word) = words.next() {
+        command.push(' ');
+        command.push_str(word);
+        if word == clock {
+            if let Some(ms) = words.next().and_then(|w| w.parse::<u64>().ok()) {
+                command.push_str(&format!(" {}", ms.saturating_sub(thought_ms).max(1)));
+            }
+        }
+    }
+
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_limits_of_searches() {
+        let go = "go wtime 60000 btime 30000 winc 1000 binc 1000";
+        assert_eq!(search_time_limit(go, true), Some(60000));
+        assert_eq!(search_time_limit(go, false), Some(30000));
+        assert_eq!(search_time_limit("go movetime 500", false), Some(500));
+        assert_eq!(search_time_limit("go depth 12", true), None);
+        assert_eq!(search_time_limit("go infinite", true), None);
+    }
+
+    #[test]
+    fn ponder_searches() {
+        assert_eq!(
+            with_moves("position startpos", &["e2e4", "e7e5"]),
+            "position startpos moves e2e4 e7e5"
+        );
+        assert_eq!(
+            with_moves("position startpos moves d2d4", &["d7d5", "c2c4"]),
+            "position startpos moves d2d4 d7d5 c2c4"
+        );
+
+        let go = "go wtime 60000 btime 30000 winc 1000 binc 1000";
+        assert_eq!(
+            ponder_command(go, true, 2500),
+            "go ponder wtime 57500 btime 30000 winc 1000 binc 1000"
+        );
+        assert_eq!(
+            ponder_command("go depth 8", false, 100),
+            "go ponder depth 8"
+        );
+        assert_eq!(search_time_limit(&ponder_command(go, false, 0), true), None);
+    }
+
+    #[test]
+    fn parse_options() {
+        let hash =
+            EngineOption::parse("option name Hash type spin default 16 min 1 max 1024").unwrap();
+        assert_eq!(hash.name, "Hash");
+        assert_eq!(hash.ui_element, UiElement::Spin { min: 1, max: 1024 });
+        assert_eq!(hash.default.as_deref(), Some("16"));
+        assert_eq!(
+            hash.setoption("4096").as_deref(),
+            Some("setoption name Hash value 1024")
+        );
+
+        let style = EngineOption::parse(
+            "option name Playing Style type combo default Normal var Solid var Normal var Risky Play",
+        )
+        .unwrap();
+        assert_eq!(style.name, "Playing Style");
+        assert_eq!(
+            style.ui_element,
+            UiElement::Combo(vec!["Solid".into(), "Normal".into(), "Risky Play".into()])
+        );
+        assert_eq!(style.setoption("Chaotic"), None);
+
+        let book =
+            EngineOption::parse("option name Book File type string default <empty>").unwrap();
+        assert_eq!(book.default.as_deref(), Some(""));
+
+        let clear = EngineOption::parse("option name Clear Hash type button").unwrap();
+        assert_eq!(clear.ui_element, UiElement::Button);
+        assert_eq!(
+            clear.setoption("").as_deref(),
+            Some("setoption name Clear Hash")
+        );
+
+        assert!(EngineOption::parse("info depth 1").is_none());
+    }
+
+    #[test]
+    fn parse_info() {
+        let info = EngineInfo::parse(
+            "info depth 12 seldepth 18 multipv 2 score cp -35 upperbound nodes 123456 nps 654321 hashfull 17 tbhits 0 time 188 pv e7e5 g1f3 b8c6",
+        )
+        .unwrap();
+        assert_eq!(info.depth, Some(12));
+        assert_eq!(info.seldepth, Some(18));
+        assert_eq!(info.multipv, Some(2));
+        assert_eq!(info.score, Some(Score::Cp(-35)));
+        assert_eq!(info.bound, Bound::Upper);
+        assert_eq!(info.nodes, Some(123456));
+        assert_eq!(info.nps, Some(654321));
+        assert_eq!(info.hashfull, Some(17));
+        assert_eq!(info.time, Some(188));
+        assert_eq!(info.pv, vec!["e7e5", "g1f3", "b8c6"]);
+
+        let mate = EngineInfo::parse("info score mate -3 depth 9").unwrap();
+        assert_eq!(mate.score, Some(Score::Mate(-3)));
+        assert_eq!(mate.depth, Some(9));
+
+        let current = EngineInfo::parse("info depth 5 currmove e2e4 currmovenumber 1").unwrap();
+        assert_eq!(current.currmove.as_deref(), Some("e2e4"));
+        assert!(current.pv.is_empty());
+
+        assert!(EngineInfo::parse("bestmove e2e4").is_none());
+    }
+
+    #[test]
+    fn scores_for_white() {
+        assert_eq!(Score::Cp(0).win_percent(), 50.0);
+        assert!(Score::Cp(300).win_percent() > 75.0);
+        assert!(
+            (Score::Cp(-120).white_win_percent(false) - Score::Cp(120).win_percent()).abs() < 0.01
+        );
+        assert_eq!(Score::Mate(3).white_win_percent(false), 0.0);
+
+        assert_eq!(Score::Cp(35).white_text(true), "+0.35");
+        assert_eq!(Score::Cp(35).white_text(false), "-0.35");
+        assert_eq!(Score::Mate(3).white_text(true), "M3");
+        assert_eq!(Score::Mate(-2).white_text(true), "-M2");
+        assert_eq!(Score::Mate(3).white_text(false), "-M3");
+        assert_eq!(Score::Mate(0).white_text(false), "M0");
+    }
+}
@@ -1,30 +1,51 @@
-use std::path::Path;
-use std::time::Instant;
+use std::path::{Path, PathBuf};
 
-use super::config::{Clock, GameMode, PromotionChoice, Promotions, UIConfig};
-use super::engine::{Engine1, Engine2, EngineStatus, UIengine};
+use super::analysis::Analysis;
+use super::chessboard::{ChessBoard, Highlights};
+use super::config::{
+    format_clock, BoardColors, Clock, GameMode, PromotionChoice, Promotions, SearchLimit, UIConfig,
+};
+use super::engine::{EngineSlot, EngineStatus, UIengine};
+use super::engine_manager::{EngineManager, EngineManagerMessage};
+use super::eval_graph::eval_graph;
+use super::game::{can_mate, draw_by_rule, AnnotationColor, GameTree, Navigation, NodeId};
+use super::notation::{move_to_san, uci_to_move};
+use super::openings::{self, Opening};
+use super::persistence;
+use super::pgn;
 use super::settings::{SettingsMessage, SettingsTab};
 use super::styling::button::CustomButtonStyle;
 use super::styling::container::container_appearance;
-use super::tournament::{self, Tournament};
+use super::supervisor::EngineError;
+use super::theme::PieceSet;
+use super::tournament::{GameResult, Tournament};
+use super::tournament_tab::{TournamentMessage, TournamentTab};
+use super::uci::EngineInfo;
 use crate::board::defs::{Pieces, Squares, SQUARE_NAME};
 use crate::board::Board;
-use crate::defs::{Sides, Square};
+use crate::defs::{Side, Sides, Square};
 use crate::movegen::defs::{print_bitboard, Move, MoveList, MoveType, Shift};
 use crate::movegen::MoveGenerator;
 use iced::alignment::{Horizontal, Vertical};
-use iced::theme::ProgressBar;
-use iced::widget::container::StyleSheet;
+use iced::widget::container::{self, StyleSheet};
 use iced::widget::{
-    column, image, progress_bar, responsive, row, Button, Column, Container, Image, Radio, Row,
-    Svg, Text,
+    column, image, responsive, row, scrollable, Button, Column, Container, Image, Radio, Row,
+    Space, Svg, Text,
 };
 use iced::{
-    executor, Alignment, Application, Color, Command, Element, Length, Sandbox, Settings, Size,
-    Subscription, Theme,
+    executor, keyboard, Alignment, Application, Background, Color, Command, Element, Length,
+    Sandbox, Settings, Size, Subscription, Theme,
 };
+use iced_aw::{TabLabel, Tabs};
 use tokio::sync::mpsc::Sender;
 
+const SIDE_PANEL_WIDTH: f32 = 340.0;
+const EVAL_BAR_WIDTH: f32 = 30.0;
+const CONTROLS_HEIGHT: f32 = 150.0;
+const MIN_BOARD_SIZE: f32 = 240.0;
+// Part of the distance to the new evaluation the bar moves each frame.
+const EVAL_ANIMATION_STEP: f32 = 0.15;
+
 pub struct Editor {
     board: Board,
     engine1: UIengine,
@@ -38,8 +59,30 @@ pub struct Editor {
     engine2_sender: Option<Sender<String>>,
     highlighted_squares: Vec<Square>,
     promotion: Promotions,
+    clock: Clock,
+    // Set when the game ended, engines don't move after that.
+    game_over: bool,
+    // The last engine that failed, until it is restarted.
+    engine_error: Option<(EngineSlot, EngineError)>,
+    // The evaluation bar moves towards the target a bit every frame.
     eval: f32,
-    tournament: Option<Tournament>,
+    eval_target: f32,
+    eval_label: String,
+    tournament_tab: TournamentTab,
+    game: GameTree,
+    engine_manager: EngineManager,
+    side_panel: SidePanel,
+    analysis: Analysis,
+    analysis_engine: UIengine,
+    analysis_sender: Option<Sender<String>>,
+    analysing: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SidePanel {
+    Settings,
+    Engines,
+    Tournament,
 }
 
 #[derive(Debug, Clone)]
@@ -47,22 +90,42 @@ pub enum Message {
     Settings(SettingsMessage),
     ChangeSettings(Option<UIConfig>),
     SelectSquare(Option<Square>),
-    EngineMove(Option<usize>, Option<usize>, Option<usize>, Option<String>),
+    // The engine's bestmove, or how the game ended when it had none.
+    EngineMove(String),
     EventOccurred(iced::Event),
     StartEngine,
-    EngineReady(Sender<String>),
+    EngineReady(EngineSlot, Sender<String>),
     EngineStopped(bool),
     ChangeStartPos,
-    SelectSideToMove(usize),
     PromotionSelected(PromotionChoice),
     Tick,
     UpdateTime,
     UpdateEval(f32),
+    AnimateEval,
     RawMove(Vec<String>),
     StartTournament,
+    ResumeTournament,
+    StopTournament,
+    Tournament(TournamentMessage),
     NextGame,
-    GameFinished(String),
-    LogResult(String),
+    ToggleArrow(Square, Square, AnnotationColor),
+    ToggleSquareMark(Square, AnnotationColor),
+    ClearAnnotations,
+    Navigate(Navigation),
+    GoToMove(NodeId),
+    CopyPgn,
+    PastePgn,
+    PgnPasted(Option<String>),
+    EngineManager(EngineManagerMessage),
+    SelectSidePanel(SidePanel),
+    SendToEngine(PathBuf, String),
+    EngineInfo(String, EngineInfo),
+    ToggleAnalysis,
+    AnalysisReady(Sender<String>),
+    AnalysisStopped,
+    PlayLine(usize),
+    EngineFailed(EngineSlot, EngineError),
+    RestartEngine(EngineSlot),
 }
 
 pub fn run() -> iced::Result {
@@ -82,33 +145,52 @@ impl Application for Editor {
     type Flags = ();
 
     fn new(_flags: Self::Flags) -> (Self, Command<Message>) {
-        (
-            Self {
-                board: Board::build(),
-                engine1: UIengine::new(
-                    "/Users/wouter/personal/rust/chess-engine/target/release/chess-engine"
-                        .to_string(),
-                    3,
-                ),
-                engine2: UIengine::new(
-                    "/Users/wouter/personal/rust/chess-engine/target/debug/chess-engine"
-                        .to_string(),
-                    3,
-                ),
-                engine1_status: EngineStatus::TurnedOff,
-                engine2_status: EngineStatus::TurnedOff,
-                movegen: MoveGenerator::new(),
-                settings: SettingsTab::new(),
-                from_square: None,
-                engine1_sender: None,
-                engine2_sender: None,
-                highlighted_squares: vec![],
-                promotion: Promotions::default(),
-                eval: 50.0,
-                tournament: None,
-            },
-            Command::none(),
-        )
+        let config = persistence::load();
+        let engine_manager =
+            EngineManager::new(config.engines.clone(), config.engine_slots.clone());
+        let tournament_tab = TournamentTab::new(
+            config.tournament.clone(),
+            engine_manager.engine_names(),
+            persistence::load_tournament().is_some_and(|tournament| !tournament.is_over()),
+        );
+        let settings = SettingsTab::from_config(config);
+        let engine1 = UIengine::new(String::new(), settings.search_depth);
+        let engine2 = UIengine::new(String::new(), settings.search_depth);
+        let analysis_engine = UIengine::new(String::new(), settings.search_depth);
+        let clock = Clock::with_time_control(&settings.time_control);
+
+        let mut editor = Self {
+            board: Board::build(),
+            engine1,
+            engine2,
+            engine1_status: EngineStatus::TurnedOff,
+            engine2_status: EngineStatus::TurnedOff,
+            movegen: MoveGenerator::new(),
+            settings,
+            from_square: None,
+            engine1_sender: None,
+            engine2_sender: None,
+            highlighted_squares: vec![],
+            promotion: Promotions::default(),
+            clock,
+            game_over: false,
+            engine_error: None,
+            eval: 50.0,
+            eval_target: 50.0,
+            eval_label: String::new(),
+            tournament_tab,
+            game: GameTree::default(),
+            engine_manager,
+            side_panel: SidePanel::Settings,
+            analysis: Analysis::default(),
+            analysis_engine,
+            analysis_sender: None,
+            analysing: false,
+        };
+        editor.assign_engines();
+        editor.assign_analysis_engine();
+
+        (editor, Command::none())
     }
     fn title(&self) -> String {
         String::from("Chess app")
@@ -116,6 +198,12 @@ impl Application for Editor {
 
     fn update(&mut self, message: self::Message) -> Command<Message> {
         match (self.from_square, message) {
+            // Clicks only move the pieces the user plays with, like drags.
+            (_, Message::SelectSquare(_)) if self.movable_side().is_none() => {
+                self.from_square = None;
+                self.highlighted_squares.clear();
+                Command::none()
+            }
             (None, Message::SelectSquare(pos)) => {
                 let side = self.board.side_to_move();
                 let color = self.board.color_on(pos);
@@ -128,57 +216,9 @@ impl Application for Editor {
                     self.from_square = pos;
                 }
 
-                let mut legal_moves = MoveList::new();
-                let piece = &self.board.piece_on(pos);
-                // Get all psuedo legal moves for the position. and the selected piece on the
-                // square
-                if let Some(piece) = piece {
-                    match *piece {
-                        Pieces::PAWN => {
-                            self.movegen
-                                .pawns(&self.board, &mut legal_moves, MoveType::All)
-                        }
-                        Pieces::KING => self.movegen.piece(
-                            &self.board,
-                            Pieces::KING,
-                            &mut legal_moves,
-                            MoveType::All,
-                        ),
-                        Pieces::QUEEN => self.movegen.piece(
-                            &self.board,
-                            Pieces::QUEEN,
-                            &mut legal_moves,
-                            MoveType::All,
-                        ),
-                        Pieces::ROOK => self.movegen.piece(
-                            &self.board,
-                            Pieces::ROOK,
-                            &mut legal_moves,
-                            MoveType::All,
-                        ),
-                        Pieces::BISHOP => self.movegen.piece(
-                            &self.board,
-                            Pieces::BISHOP,
-                            &mut legal_moves,
-                            MoveType::All,
-                        ),
-                        Pieces::KNIGHT => self.movegen.piece(
-                            &self.board,
-                            Pieces::KNIGHT,
-                            &mut legal_moves,
-                            MoveType::All,
-                        ),
-                        _ => (),
-                    }
-                }
-
-                for move_data in legal_moves
-                    .moves
-                    .iter()
-                    .filter(|move_data| move_data.from() == pos.unwrap())
-                {
-                    // Get all legal moves for the square and change it's color
-                    self.highlighted_squares.push(move_data.to());
+                // Show where the selected piece can go
+                if let Some(square) = pos {
+                    self.highlighted_squares = self.legal_targets(square);
                 }
                 Command::none()
             }
@@ -193,57 +233,9 @@ impl Application for Editor {
                     // If user clicked on another square with a piece of his own side, update from_square and legal moves
                     self.from_square = to;
 
-                    let mut legal_moves = MoveList::new();
-                    let piece = self.board.piece_on(to);
-
-                    // Get all pseudo-legal moves for the position and the selected piece on the square
-                    if let Some(piece) = piece {
-                        match piece {
-                            Pieces::PAWN => {
-                                self.movegen
-                                    .pawns(&self.board, &mut legal_moves, MoveType::All)
-                            }
-                            Pieces::KING => self.movegen.piece(
-                                &self.board,
-                                Pieces::KING,
-                                &mut legal_moves,
-                                MoveType::All,
-                            ),
-                            Pieces::QUEEN => self.movegen.piece(
-                                &self.board,
-                                Pieces::QUEEN,
-                                &mut legal_moves,
-                                MoveType::All,
-                            ),
-                            Pieces::ROOK => self.movegen.piece(
-                                &self.board,
-                                Pieces::ROOK,
-                                &mut legal_moves,
-                                MoveType::All,
-                            ),
-                            Pieces::BISHOP => self.movegen.piece(
-                                &self.board,
-                                Pieces::BISHOP,
-                                &mut legal_moves,
-                                MoveType::All,
-                            ),
-                            Pieces::KNIGHT => self.movegen.piece(
-                                &self.board,
-                                Pieces::KNIGHT,
-                                &mut legal_moves,
-                                MoveType::All,
-                            ),
-                            _ => (),
-                        }
-                    }
-
-                    for move_data in legal_moves
-                        .moves
-                        .iter()
-                        .filter(|move_data| move_data.from() == to.unwrap())
-                    {
-                        // Highlight squares for all legal moves from the selected square
-                        self.highlighted_squares.push(move_data.to());
+                    // Highlight squares for all legal moves from the selected square
+                    if let Some(square) = to {
+                        self.highlighted_squares = self.legal_targets(square);
                     }
 
                     return Command::none();
@@ -257,7 +249,9 @@ impl Application for Editor {
                 if self.board.piece_on(Some(from)).unwrap() == Pieces::PAWN {
                     // Check if the pawn is moving to the promotion rank
                     let promotion_rank = Board::promotion_rank(side);
-                    if to.unwrap() / 8 == promotion_rank {
+                    if to.unwrap() / 8 == promotion_rank
+                        && self.legal_targets(from).contains(&to.unwrap())
+                    {
                         // Show the promotion prompt
                         self.promotion.show_promotion_prompt = true;
                         self.promotion.promotion_square = to;
@@ -273,7 +267,7 @@ impl Application for Editor {
 
                 // Check if move is legal
                 if legal_moves.moves.iter().any(|x| x.data == move_data) {
-                    self.board.make_move(Move::new(move_data), &self.movegen);
+                    self.play_move(Move::new(move_data));
                 } else {
                     println!(
                         "{:?}\n",
@@ -292,146 +286,160 @@ impl Application for Editor {
                 }
 
                 // Only if Engine is playing against humans and only if it is not the player's turn
-                if self.settings.game_mode == GameMode::PlayerEngine {
-                    if !(self.settings.player_side as usize == self.board.side_to_move()) {
-                        if let Some(sender) = &self.engine1_sender {
-                            if let Err(e) = sender.blocking_send(self.board.create_fen()) {
-                                eprintln!("Lost connection with the engine: {}", e);
-                            }
-                        }
+                if self.settings.game_mode == GameMode::PlayerEngine
+                    && self.settings.player_side as usize != self.board.side_to_move()
+                {
+                    // A mated or stalemated engine isn't asked for a move.
+                    if let Some(command) = self.mate_or_stalemate() {
+                        return command;
                     }
+                    let request = self.search_request();
+                    return self.send_to_engine(EngineSlot::White, request);
                 }
 
                 Command::none()
             }
-            (_, Message::EngineMove(from, to, promotion, mate)) => {
+            (_, Message::EngineMove(bestmove)) => {
                 // Let engine make move
+                if self.game_over {
+                    return Command::none();
+                }
+
+                // Whatever the engine answers, the board decides if the
+                // game is over.
+                if let Some(command) = self.mate_or_stalemate() {
+                    return command;
+                }
 
-                let fen = self.board.create_fen().clone();
-                if mate == Some("mate".to_string()) {
-                    // Bit weird but we flipped already
-                    let side_message = if self.board.side_to_move() != Sides::BLACK {
-                        "black"
+                // A move that isn't legal on the board, like a null move
+                // while there are legal ones, loses the game instead of
+                // being played.
+                let fen = self.board.create_fen();
+                let Some(m) = uci_to_move(&self.board, &self.movegen, &bestmove) else {
+                    let (result, side) = if self.board.side_to_move() == Sides::WHITE {
+                        ("0-1", "White")
                     } else {
-                        "white"
+                        ("1-0", "Black")
                     };
-                    return Command::perform(
-                        async move {
-                            Message::LogResult(format!(
-                                "Check mate, {} won the game\nFen: {}",
-                                side_message, fen
-                            ))
-                        },
-                        |msg| msg,
+                    return self.end_game(
+                        result,
+                        "rules infraction",
+                        format!(
+                            "{} engine played the illegal move {}\nFen: {}",
+                            side, bestmove, fen
+                        ),
                     );
-                }
+                };
+                self.play_move(m);
 
-                if mate == Some("draw".to_string()) {
-                    return Command::perform(
-                        async move { Message::LogResult(format!("Game ended in a draw\nFen: {}", fen)) },
-                        |msg| msg,
-                    );
+                if let Some(command) = self.judge_engine_game() {
+                    return command;
                 }
 
-                if from.unwrap() == 0 && to.unwrap() == 0 {
-                    return Command::perform(
-                        async move {
-                            Message::LogResult(format!(
-                                "Illigal move recorded {:?}, {:?}\nFen: {}",
-                                from, to, fen
-                            ))
-                        },
-                        |msg| msg,
-                    );
+                // Against a player the engine waits for the player's move,
+                // pondering if it can.
+                if self.settings.game_mode != GameMode::EngineEngine {
+                    return Command::none();
                 }
-
-                let side = self.board.side_to_move() == Sides::WHITE;
-                let move_data = self
-                    .board
-                    .generate_move_data(&from.unwrap(), &to, side, promotion);
-
-                let is_made = self.board.make_move(Move::new(move_data), &self.movegen);
-
-                //debug
-                if !is_made {
-                    println!(
-                        "Move: {}",
-                        SQUARE_NAME[Move::new(move_data).from()].to_owned()
-                            + SQUARE_NAME[Move::new(move_data).to()]
-                    );
-
-                    for mv in &self.board.history.list {
-                        if SQUARE_NAME[mv.next_move.from()].to_owned()
-                            + SQUARE_NAME[mv.next_move.to()]
-                            != "a1a1"
-                        {
-                            println!(
-                                "{:?}",
-                                SQUARE_NAME[mv.next_move.from()].to_owned()
-                                    + SQUARE_NAME[mv.next_move.to()]
-                            );
-                        }
-                    }
+                let request = self.search_request();
+                let slot = if self.board.side_to_move() == Sides::WHITE {
+                    EngineSlot::White
+                } else {
+                    EngineSlot::Black
+                };
+                self.send_to_engine(slot, request)
+            }
+            (_, Message::StartTournament) => {
+                let engines = self.tournament_tab.engines();
+                if engines.len() < 2 {
+                    self.tournament_tab.status = "Pick at least two engines".to_string();
+                    return Command::none();
                 }
 
-                if self.board.side_to_move() == Sides::WHITE {
-                    if let Some(sender) = &self.engine1_sender {
-                        if let Err(e) = sender.blocking_send(self.board.create_fen()) {
-                            eprintln!("Lost connection with the engine1: {}", e);
-                        }
-                    }
-                } else if self.board.side_to_move() == Sides::BLACK {
-                    if let Some(sender) = &self.engine2_sender {
-                        if let Err(e) = sender.blocking_send(self.board.create_fen()) {
-                            println!("Lost connection with the engine2: {}", e);
+                let config = &self.tournament_tab.config;
+                let mut tournament = Tournament::new(config.format, engines, config.rounds);
+                tournament.sprt = config.sprt_enabled.then_some(config.sprt);
+                let opening_file = config.opening_file.trim();
+                if !opening_file.is_empty() {
+                    match openings::load(Path::new(opening_file), config.opening_plies) {
+                        Ok(suite) => tournament.set_openings(
+                            suite,
+                            config.opening_order,
+                            config.opening_seed,
+                        ),
+                        Err(e) => {
+                            self.tournament_tab.status = e;
+                            return Command::none();
                         }
                     }
                 }
-
-                Command::none()
+                let mut header = format!(
+                    "{} tournament, {} rounds: {}",
+                    tournament.format,
+                    tournament.rounds,
+                    tournament.engines.join(", ")
+                );
+                if let Some(sprt) = tournament.sprt_summary() {
+                    header.push_str(&format!("\n{}", sprt));
+                }
+                if let Err(e) = persistence::log_tournament(&header, true) {
+                    eprintln!("Could not write the tournament log: {}", e);
+                }
+                self.start_tournament(tournament)
             }
-            (_, Message::LogResult(result)) => {
-                let _ = self
-                    .tournament
-                    .as_mut()
-                    .unwrap()
-                    .log_result(&result.to_owned());
-                Command::perform(async { Message::NextGame }, |msg| msg)
+            (_, Message::ResumeTournament) => match persistence::load_tournament() {
+                Some(tournament) => self.start_tournament(tournament),
+                None => {
+                    self.tournament_tab.resumable = false;
+                    self.tournament_tab.status = "No tournament to resume".to_string();
+                    Command::none()
+                }
+            },
+            (_, Message::StopTournament) => {
+                self.stop_tournament();
+                self.tournament_tab.status.clear();
+                Command::none()
             }
-            (_, Message::StartTournament) => {
-                self.engine1_status = EngineStatus::TurnedOn;
-                self.engine2_status = EngineStatus::TurnedOn;
-
-                self.tournament = Some(Tournament::new(10, "./ui/tournament_log.txt").unwrap());
-
-                Command::perform(async { Message::NextGame }, |msg| msg)
+            (_, Message::Tournament(message)) => {
+                self.tournament_tab.update(message);
+                self.save_config();
+                Command::none()
             }
             (_, Message::NextGame) => {
-                // Check if the tournament is over
-                if let Some(tournament) = &self.tournament {
-                    if tournament.tournament_over() {
-                        println!("Tournament over!");
-                        return Command::none();
-                    }
+                let next = self
+                    .tournament_tab
+                    .running
+                    .as_mut()
+                    .map(|tournament| tournament.next_game());
+                if next == Some(None) {
+                    self.finish_tournament();
+                    return Command::none();
                 }
+
                 // Start the next game
                 // Reset board and start engines
                 self.board = Board::build();
+                self.game = GameTree::default();
+                if let Some(Some(index)) = next {
+                    if let Err(e) = self.start_tournament_game(index) {
+                        self.stop_tournament();
+                        self.tournament_tab.status = e;
+                        return Command::none();
+                    }
+                }
+                self.new_game_for_engines();
+                self.new_clock();
+                self.clock.start(self.board.side_to_move());
+                self.analyse_position();
                 self.engine1_status = EngineStatus::TurnedOn;
                 self.engine2_status = EngineStatus::TurnedOn;
-                if let Some(sender) = &self.engine1_sender {
-                    sender.blocking_send(self.board.create_fen()).unwrap();
-                }
-                if let Some(sender) = &self.engine2_sender {
-                    sender.blocking_send(self.board.create_fen()).unwrap();
-                }
-                Command::none()
-            }
-            (_, Message::GameFinished(result)) => {
-                if let Some(tournament) = &mut self.tournament {
-                    tournament.log_result(&result).unwrap();
-                }
-                Command::perform(async { Message::NextGame }, |msg| msg)
+                // An opening can leave either side to move.
+                let request = self.search_request();
+                let slot = match self.engine_to_move() {
+                    Some(EngineSlot::Black) => EngineSlot::Black,
+                    _ => EngineSlot::White,
+                };
+                self.send_to_engine(slot, request)
             }
             (_, Message::StartEngine) => {
                 if self.settings.game_mode == GameMode::EngineEngine {
@@ -450,11 +458,11 @@ impl Application for Editor {
                             }
                         }
                         _ => {
-                            if let Some(sender) = &self.engine1_sender {
-                                sender
-                                    .blocking_send(String::from("STOP"))
-                                    .expect("Error quiting engine");
-                                self.engine1_sender = None;
+                            if let Some(sender) = self.engine1_sender.take() {
+                                // An engine that is gone is stopped already.
+                                if let Err(e) = sender.blocking_send(String::from("STOP")) {
+                                    eprintln!("Could not stop the engine: {}", e);
+                                }
                             }
                         }
                     }
@@ -466,6 +474,7 @@ impl Application for Editor {
                             if Path::new(&self.engine1.engine_path).exists() {
                                 self.engine1.position = self.board.create_fen();
                                 self.engine1_status = EngineStatus::TurnedOn;
+                                self.clock.start(self.board.side_to_move());
                             } else {
                                 println!("Invalid engine 1 path");
                             }
@@ -475,50 +484,273 @@ impl Application for Editor {
                         }
                     }
                     _ => {
-                        if let Some(sender) = &self.engine1_sender {
-                            sender
-                                .blocking_send(String::from("STOP"))
-                                .expect("Error quiting engine");
-                            self.engine1_sender = None;
+                        if let Some(sender) = self.engine1_sender.take() {
+                            if let Err(e) = sender.blocking_send(String::from("STOP")) {
+                                eprintln!("Could not stop the engine: {}", e);
+                            }
                         }
                     }
                 }
                 Command::none()
             }
-            (_, Message::EngineReady(message)) => {
-                if self.engine1_status == EngineStatus::TurnedOn && self.engine1_sender.is_none() {
-                    self.engine1_sender = Some(message);
-                } else if self.engine2_status == EngineStatus::TurnedOn
-                    && self.engine2_sender.is_none()
-                {
-                    self.engine2_sender = Some(message);
+            (_, Message::EngineReady(slot, sender)) => {
+                match slot {
+                    EngineSlot::White if self.engine1_status == EngineStatus::TurnedOn => {
+                        self.engine1_sender = Some(sender);
+                    }
+                    EngineSlot::Black if self.engine2_status == EngineStatus::TurnedOn => {
+                        self.engine2_sender = Some(sender);
+                    }
+                    _ => return Command::none(),
                 }
 
-                if self.settings.game_mode == GameMode::EngineEngine {
-                    if let Some(engine1_sender) = &self.engine1_sender {
-                        if let Err(e) = engine1_sender.blocking_send(self.board.create_fen()) {
-                            eprintln!("Lost connection with engine 1: {}", e);
-                        }
+                // An engine that starts, or restarts, while it is to move
+                // gets the position right away.
+                if !self.game_over && self.engine_to_move() == Some(slot) {
+                    let request = self.search_request();
+                    return self.send_to_engine(slot, request);
+                }
+                Command::none()
+            }
+            (_, Message::EngineFailed(slot, error)) => {
+                eprintln!("{} engine failed: {}", slot, error);
+                if slot == EngineSlot::Analysis {
+                    self.engine_error = Some((slot, error));
+                    return Command::none();
+                }
+
+                // The second engine only plays in engine vs engine.
+                let playing = self.engine1_status == EngineStatus::TurnedOn
+                    && (slot == EngineSlot::White
+                        || self.settings.game_mode == GameMode::EngineEngine);
+                if !playing {
+                    return Command::none();
+                }
+
+                match slot {
+                    EngineSlot::White => self.engine1_sender = None,
+                    _ => self.engine2_sender = None,
+                }
+
+                // In a tournament the engine loses the game and is restarted
+                // for the next one. Otherwise the game waits for the user to
+                // restart it.
+                if self.tournament_tab.running.is_none() {
+                    self.engine_error = Some((slot, error));
+                    return Command::none();
+                }
+                self.restart_engine(slot);
+                if self.game_over {
+                    return Command::none();
+                }
+                let (result, side) = if self.engine_side(slot) == Sides::WHITE {
+                    ("0-1", "white")
+                } else {
+                    ("1-0", "black")
+                };
+                let fen = self.board.create_fen();
+                self.end_game(
+                    result,
+                    "abandoned",
+                    format!(
+                        "The {} engine failed, it loses the game\n{}\nFen: {}",
+                        side, error, fen
+                    ),
+                )
+            }
+            (_, Message::RestartEngine(slot)) => {
+                self.restart_engine(slot);
+                Command::none()
+            }
+            (_, Message::EventOccurred(event)) => {
+                if let iced::Event::Keyboard(keyboard::Event::KeyPressed { key_code, .. }) = event {
+                    let navigation = match key_code {
+                        keyboard::KeyCode::Left => Some(Navigation::Back),
+                        keyboard::KeyCode::Right => Some(Navigation::Forward),
+                        keyboard::KeyCode::Home => Some(Navigation::Start),
+                        keyboard::KeyCode::End => Some(Navigation::End),
+                        _ => None,
+                    };
+
+                    if let Some(navigation) = navigation {
+                        self.game.navigate(navigation);
+                        self.show_current_node();
                     }
                 }
                 Command::none()
             }
-            (_, Message::EventOccurred(_event)) => {
-                //
+            (_, Message::ToggleArrow(from, to, color)) => {
+                self.game
+                    .current_node_mut()
+                    .annotations
+                    .toggle_arrow(from, to, color);
+                Command::none()
+            }
+            (_, Message::ToggleSquareMark(square, color)) => {
+                self.game
+                    .current_node_mut()
+                    .annotations
+                    .toggle_square(square, color);
+                Command::none()
+            }
+            (_, Message::ClearAnnotations) => {
+                self.game.current_node_mut().annotations.clear();
+                Command::none()
+            }
+            (_, Message::Navigate(navigation)) => {
+                self.game.navigate(navigation);
+                self.show_current_node();
+                Command::none()
+            }
+            (_, Message::GoToMove(node)) => {
+                self.game.go_to(node);
+                self.show_current_node();
+                Command::none()
+            }
+            (_, Message::CopyPgn) => iced::clipboard::write(pgn::export(&self.game)),
+            (_, Message::PastePgn) => iced::clipboard::read(Message::PgnPasted),
+            (_, Message::PgnPasted(text)) => {
+                if let Some(text) = text {
+                    match pgn::import(&text, &self.movegen) {
+                        Ok(game) => {
+                            self.game = game;
+                            self.new_game_for_engines();
+                            self.show_current_node();
+                        }
+                        Err(e) => eprintln!("{}", e),
+                    }
+                }
                 Command::none()
             }
             (_, Message::Settings(message)) => self.settings.update(message),
             (_, Message::ChangeSettings(message)) => {
                 if let Some(settings) = message {
+                    // The settings tab already holds the new number of lines,
+                    // so it is compared with what the analysis engine was
+                    // given.
+                    let multipv = settings.multipv.to_string();
+                    let given = self
+                        .analysis_engine
+                        .options
+                        .iter_mut()
+                        .find(|(name, _)| name == "MultiPV");
+                    if let Some((_, value)) = given.filter(|(_, value)| *value != multipv) {
+                        *value = multipv;
+                        self.send_to_analysis(format!(
+                            "setoption name MultiPV value {}",
+                            settings.multipv
+                        ));
+                    }
+                    self.settings.multipv = settings.multipv;
                     self.settings.flip_board = settings.flip_board;
                     self.settings.show_coords = settings.show_coordinates;
                     self.settings.search_depth = settings.search_depth;
                     self.settings.game_mode = settings.game_mode;
+                    self.settings.player_side = settings.player_side;
+                    self.settings.theme = settings.theme;
+                    self.settings.time_control = settings.time_control;
+                    self.settings.search_limit = settings.search_limit;
+                    self.settings.search_nodes = settings.search_nodes;
+                    self.settings.search_move_time_ms = settings.search_move_time_ms;
+                    self.settings.safety_margin_ms = settings.safety_margin_ms;
+                }
+
+                self.assign_engines();
+                self.assign_analysis_engine();
+                if !self.clock.running {
+                    self.new_clock();
+                }
+                self.save_config();
+                Command::none()
+            }
+            (_, Message::EngineManager(message)) => {
+                let command = self.engine_manager.update(message);
+                self.tournament_tab.available = self.engine_manager.engine_names();
+                self.assign_engines();
+                self.assign_analysis_engine();
+                self.save_config();
+                command
+            }
+            (_, Message::SendToEngine(path, command)) => {
+                let engines = [
+                    (&self.engine1, &self.engine1_sender),
+                    (&self.engine2, &self.engine2_sender),
+                ];
+                for (engine, sender) in engines {
+                    if engine.engine_path != path {
+                        continue;
+                    }
+                    if let Some(sender) = sender {
+                        if let Err(e) = sender.blocking_send(command.clone()) {
+                            eprintln!("Lost connection with the engine: {}", e);
+                        }
+                    }
                 }
                 Command::none()
             }
-            (_, Message::SelectSideToMove(_message)) => {
-                self.board.swap_side();
+            (_, Message::EngineInfo(fen, info)) => {
+                self.analysis.update(&fen, info, &self.movegen);
+
+                // Scores about the position on the board are kept with the
+                // game, for the evaluation graph.
+                if self.analysis.fen() == self.board.create_fen() {
+                    if let Some(score) = self.analysis.best().and_then(|line| line.info.score) {
+                        self.game.current_node_mut().score = Some(score);
+                    }
+                    if let Some((eval, label)) = self.analysis.white_eval() {
+                        self.eval_target = eval;
+                        self.eval_label = label;
+                    }
+                }
+                Command::none()
+            }
+            (_, Message::ToggleAnalysis) => {
+                if self.analysing {
+                    self.send_to_analysis(String::from("quit"));
+                    self.analysing = false;
+                    self.analysis_sender = None;
+                } else if Path::new(&self.analysis_engine.engine_path).exists() {
+                    self.analysis_engine.position = self.board.create_fen();
+                    self.analysis.clear();
+                    self.analysing = true;
+                } else {
+                    println!("Invalid analysis engine path");
+                }
+                Command::none()
+            }
+            (_, Message::AnalysisReady(sender)) => {
+                self.analysis_sender = Some(sender);
+                self.analyse_position();
+                Command::none()
+            }
+            (_, Message::AnalysisStopped) => {
+                self.analysing = false;
+                self.analysis_sender = None;
+                Command::none()
+            }
+            (_, Message::PlayLine(index)) => {
+                // Only a line about the position on the board can be played.
+                let line = match self.analysis.lines.get(index) {
+                    Some(line) if self.analysis.fen() == self.board.create_fen() => {
+                        line.info.pv.clone()
+                    }
+                    _ => return Command::none(),
+                };
+
+                for uci in &line {
+                    match uci_to_move(&self.board, &self.movegen, uci) {
+                        Some(m) => {
+                            self.play_move(m);
+                        }
+                        None => break,
+                    }
+                }
+                self.from_square = None;
+                self.highlighted_squares.clear();
+                Command::none()
+            }
+            (_, Message::SelectSidePanel(panel)) => {
+                self.side_panel = panel;
                 Command::none()
             }
             (_, Message::ChangeStartPos) => {
@@ -526,10 +758,14 @@ impl Application for Editor {
                 let _ = self
                     .board
                     .read_fen(Some("3r4/3r4/3k4/8/8/8/8/3K4 w - - 0 1"));
+                self.game = GameTree::new("3r4/3r4/3k4/8/8/8/8/3K4 w - - 0 1");
+                self.new_game_for_engines();
 
                 // update engine
                 self.engine1.position = "3r4/3r4/3k4/8/8/8/8/3K4 w - - 0 1".to_string();
                 self.engine2.position = "3r4/3r4/3k4/8/8/8/8/3K4 w - - 0 1".to_string();
+                self.new_clock();
+                self.analyse_position();
                 Command::none()
             }
             (_, Message::PromotionSelected(choice)) => {
@@ -543,40 +779,43 @@ impl Application for Editor {
                         Some(choice as usize + 1), // + 1 bc queen starts at 0,
                     );
 
-                    self.board.make_move(Move::new(move_data), &self.movegen);
+                    self.play_move(Move::new(move_data));
+                    self.from_square = None;
                     self.promotion.show_promotion_prompt = false; // Hide the promotion prompt
                     self.promotion.promotion_square = None; // Reset the promotion square
                                                             //
-                    if self.settings.game_mode == GameMode::PlayerEngine {
-                        if !(self.settings.player_side as usize == self.board.side_to_move()) {
-                            if let Some(sender) = &self.engine1_sender {
-                                if let Err(e) = sender.blocking_send(self.board.create_fen()) {
-                                    eprintln!("Lost connection with the engine: {}", e);
-                                }
-                            }
+                    if self.settings.game_mode == GameMode::PlayerEngine
+                        && self.settings.player_side as usize != self.board.side_to_move()
+                    {
+                        if let Some(command) = self.mate_or_stalemate() {
+                            return command;
                         }
+                        let request = self.search_request();
+                        return self.send_to_engine(EngineSlot::White, request);
                     }
                 }
                 Command::none()
             }
             (_, Message::UpdateTime) => Command::none(),
             (_, Message::Tick) => {
-                let now = Instant::now();
-                let elapsed = now.duration_since(self.engine1.clock.last_tick).as_millis();
+                self.clock.tick();
 
-                if self.board.side_to_move() == Sides::WHITE {
-                    self.engine1.clock.wtime = self.engine1.clock.wtime.saturating_sub(elapsed);
-                } else {
-                    self.engine1.clock.btime = self.engine1.clock.btime.saturating_sub(elapsed);
+                match self.clock.flagged {
+                    Some(side) if !self.game_over => self.flag_fall(side),
+                    _ => Command::none(),
                 }
-
-                self.engine1.clock.last_tick = now;
-
-                Command::none()
             }
             (_, Message::UpdateEval(eval)) => {
-                println!("{:?}", eval);
-                self.eval = eval;
+                self.eval_target = eval;
+                Command::none()
+            }
+            (_, Message::AnimateEval) => {
+                let step = (self.eval_target - self.eval) * EVAL_ANIMATION_STEP;
+                if step.abs() < 0.1 {
+                    self.eval = self.eval_target;
+                } else {
+                    self.eval += step;
+                }
                 Command::none()
             }
             (_, Message::RawMove(themove)) => {
@@ -588,247 +827,743 @@ impl Application for Editor {
     }
 
     fn subscription(&self) -> Subscription<Message> {
-        match self.engine1_status {
-            EngineStatus::TurnedOff => iced::subscription::events().map(Message::EventOccurred),
-            _ => Subscription::batch(vec![
-                self.engine1
-                    .clone()
-                    .run_engine(std::any::TypeId::of::<Engine1>()),
-                self.engine2
-                    .clone()
-                    .run_engine(std::any::TypeId::of::<Engine2>()),
-                iced::subscription::events().map(Message::EventOccurred),
-                iced::time::every(std::time::Duration::from_millis(1000)).map(|_| Message::Tick),
-            ]),
+        let mut subscriptions = vec![iced::subscription::events().map(Message::EventOccurred)];
+
+        if self.engine1_status != EngineStatus::TurnedOff {
+            subscriptions.extend([
+                self.engine1.clone().run_engine(EngineSlot::White),
+                self.engine2.clone().run_engine(EngineSlot::Black),
+            ]);
+        }
+        // Ticks often enough to show tenths of a second, and once more after
+        // a flag fell during a move.
+        if self.clock.running || (self.clock.flagged.is_some() && !self.game_over) {
+            subscriptions.push(
+                iced::time::every(std::time::Duration::from_millis(100)).map(|_| Message::Tick),
+            );
+        }
+        if self.analysing {
+            subscriptions.push(self.analysis_engine.clone().run_analysis());
         }
+        if self.eval != self.eval_target {
+            subscriptions.push(
+                iced::time::every(std::time::Duration::from_millis(16))
+                    .map(|_| Message::AnimateEval),
+            );
+        }
+
+        Subscription::batch(subscriptions)
     }
 
     fn view(&self) -> Element<Message, iced::Renderer<Theme>> {
         let resp = responsive(move |size| {
             main_view(
                 &self.board,
-                self.settings.flip_board,
+                self.board_flipped(),
                 self.settings.show_coords,
                 self.settings.search_depth,
-                self.settings.view(),
+                Tabs::new(Message::SelectSidePanel)
+                    .push(
+                        SidePanel::Settings,
+                        self.settings.tab_label(),
+                        self.settings.view(),
+                    )
+                    .push(
+                        SidePanel::Engines,
+                        self.engine_manager.tab_label(),
+                        self.engine_manager.view(),
+                    )
+                    .push(
+                        SidePanel::Tournament,
+                        self.tournament_tab.tab_label(),
+                        self.tournament_tab.view(),
+                    )
+                    .set_active_tab(&self.side_panel)
+                    .into(),
                 self.engine1_status != EngineStatus::TurnedOff,
+                self.analysing,
                 size,
                 &self.highlighted_squares,
+                Highlights {
+                    last_move: self.last_move(),
+                    check: self.king_in_check(),
+                    selected: self.from_square,
+                },
+                self.settings.theme.board.colors(),
+                &self.settings.theme.pieces,
                 &self.promotion,
-                &self.engine1,
+                self.movable_side(),
+                self.settings.player_side as usize,
+                &self.clock,
                 self.eval,
+                &self.eval_label,
+                &self.game,
+                &self.analysis,
+                self.tournament_tab.standings(),
             )
         });
 
-        Container::new(resp).padding(1).into()
+        let mut content = Column::new();
+        if let Some((slot, error)) = &self.engine_error {
+            content = content.push(
+                Container::new(
+                    row![
+                        Text::new(format!("{} engine: {}", slot, error)).width(Length::Fill),
+                        Button::new(Text::new("Restart")).on_press(Message::RestartEngine(*slot)),
+                    ]
+                    .spacing(10)
+                    .align_items(Alignment::Center),
+                )
+                .padding(10)
+                .width(Length::Fill),
+            );
+        }
+
+        Container::new(content.push(resp)).padding(1).into()
     }
 
     fn theme(&self) -> Theme {
-        Theme::Dark
+        self.settings.theme.app.theme()
     }
 }
 
-fn main_view<'a>(
-    board: &Board,
-    flip_board: bool,
-    show_coordinates: bool,
-    _search: u32,
-    settings_tab: Element<'a, Message, iced::Renderer<Theme>>,
-    engine_started: bool,
-    _size: Size,
-    highlighted_squares: &Vec<Square>,
-    promotion: &Promotions,
-    engine: &UIengine,
-    eval: f32,
-) -> Element<'a, Message, iced::Renderer<Theme>> {
-    let mut board_col = Column::new().spacing(0).align_items(Alignment::Center);
-    let mut board_row = Row::new().spacing(0).align_items(Alignment::Center);
-    let mut promotion_counter = 0;
-    let promotion_options = vec![
-        ("/wQ.svg", PromotionChoice::Queen),
-        ("/wN.svg", PromotionChoice::Knight),
-        ("/wR.svg", PromotionChoice::Rook),
-        ("/wB.svg", PromotionChoice::Bishop),
-    ];
-
-    let timer_row = column![
-        Button::new(Text::new(format!(
-            "Black: {}:{:02}",
-            engine.clock.btime / 60000,
-            (engine.clock.btime % 60000) / 1000
-        ))),
-        // .style(
-        //     CustomButtonStyle::new()
-        //         .background_color(Color::from_rgb(1.0, 1.0, 1.0))
-        //         .as_custom()
-        // ),
-        Button::new(Text::new(format!(
-            "White: {}:{:02}",
-            engine.clock.wtime / 60000,
-            (engine.clock.wtime % 60000) / 1000
-        ))) // .style(
-            //     CustomButtonStyle::new()
-            //         .background_color(Color::from_rgb(1.0, 0.0, 0.0)) // Highlight color
-            //         .hovered()
-            //         .background_color(Color::from_rgb(0.4, 0.4, 0.8))
-            //         .pressed()
-            //         .background_color(Color::from_rgb(0.3, 0.3, 0.7))
-            //         .as_custom()
-            // ),
-    ]
-    .padding(30)
-    .spacing(90 * 8);
-
-    let ranks;
-    let files;
-    ranks = (1..=8).collect::<Vec<i32>>();
-    files = (1..=8).rev().collect::<Vec<i32>>();
-    let board_height = 100;
-    let row = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
-
-    for rank in ranks {
-        for file in &files {
-            let pos = board.get_square((rank as usize, *file as usize));
-
-            let (piece, color) = (board.piece_on(pos), board.color_on(pos));
-
-            let mut text = "";
-            let light_square = (rank + file) % 2 != 0;
-
-            // let selected = from_square == Some(pos);
-            let square_style = if highlighted_squares.contains(&pos.unwrap()) {
-                CustomButtonStyle::new()
-                    .background_color(Color::from_rgb(1.0, 0.0, 0.0)) // Highlight color
-                    .hovered()
-                    .background_color(Color::from_rgb(0.4, 0.4, 0.8))
-                    .pressed()
-                    .background_color(Color::from_rgb(0.3, 0.3, 0.7))
-            } else if light_square {
-                CustomButtonStyle::new()
-                    .background_color(Color::from_rgb(0.91, 0.741, 0.529))
-                    .hovered()
-                    .background_color(Color::from_rgb(0.91, 0.741, 0.529))
-                    .pressed()
-                    .background_color(Color::from_rgb(0.803, 0.82, 0.415))
-            } else {
-                CustomButtonStyle::new()
-                    .background_color(Color::from_rgb(0.639, 0.502, 0.329))
-                    .hovered()
-                    .background_color(Color::from_rgb(0.639, 0.502, 0.329))
-                    .pressed()
-                    .background_color(Color::from_rgb(0.666, 0.635, 0.22))
-            };
+impl Editor {
+    // Give the engine processes the engines from their slots. In player vs
+    // engine the first engine is the opponent, so it gets the engine of the
+    // side the player doesn't play. Engines and clocks that are running keep
+    // their settings.
+    fn assign_engines(&mut self) {
+        if self.engine1_status != EngineStatus::TurnedOff {
+            return;
+        }
 
-            let button;
-
-            // Show promotion pieces
-            if promotion.show_promotion_prompt
-                && promotion_counter < 4
-                && (pos
-                    == Some(promotion.promotion_square.unwrap() as usize - promotion_counter * 8))
-            {
-                let (svg_path, piece) = &promotion_options[promotion_counter];
-
-                button = Button::new(Svg::from_path(format!(
-                    "{}/pieces{}",
-                    env!("CARGO_MANIFEST_DIR"),
-                    svg_path
-                )))
-                .width(board_height)
-                .height(board_height)
-                .on_press(Message::PromotionSelected(piece.clone()))
-                .style(square_style.as_custom());
-
-                promotion_counter += 1;
-            } else {
-                // Set pieces on boad
-                button = if let Some(piece) = piece {
-                    if color == Sides::WHITE {
-                        text = match piece {
-                            Pieces::PAWN => "/wP.svg",
-                            Pieces::ROOK => "/wR.svg",
-                            Pieces::KNIGHT => "/wN.svg",
-                            Pieces::BISHOP => "/wB.svg",
-                            Pieces::QUEEN => "/wQ.svg",
-                            Pieces::KING => "/wK.svg",
-                            _ => "",
-                        };
-                    } else {
-                        text = match piece {
-                            Pieces::PAWN => "/bP.svg",
-                            Pieces::ROOK => "/bR.svg",
-                            Pieces::KNIGHT => "/bN.svg",
-                            Pieces::BISHOP => "/bB.svg",
-                            Pieces::QUEEN => "/bQ.svg",
-                            Pieces::KING => "/bK.svg",
-                            _ => "",
-                        };
-                    }
+        let playing_white = self.settings.player_side as usize == Sides::WHITE;
+        let (first, second) = if self.settings.game_mode == GameMode::PlayerEngine && playing_white
+        {
+            (EngineSlot::Black, EngineSlot::White)
+        } else {
+            (EngineSlot::White, EngineSlot::Black)
+        };
+
+        self.engine1.engine_path = self.engine_manager.engine_path(first);
+        self.engine2.engine_path = self.engine_manager.engine_path(second);
+        self.engine1.options = self.engine_manager.option_values(first);
+        self.engine2.options = self.engine_manager.option_values(second);
+        self.engine1.search_up_to = self.settings.search_depth;
+        self.engine2.search_up_to = self.settings.search_depth;
+        if let Some(definition) = self.engine_manager.engine_in(first) {
+            self.engine1.set_timeouts(definition);
+        }
+        if let Some(definition) = self.engine_manager.engine_in(second) {
+            self.engine2.set_timeouts(definition);
+        }
+    }
 
-                    Button::new(Svg::from_path(format!(
-                        "{}/pieces{}",
-                        env!("CARGO_MANIFEST_DIR"),
-                        text
-                    )))
-                    .width(board_height)
-                    .height(board_height)
-                    .on_press(Message::SelectSquare(pos))
-                    .style(square_style.as_custom())
-                } else {
-                    Button::new(Text::new(""))
-                        .width(board_height)
-                        .height(board_height)
-                        .on_press(Message::SelectSquare(pos))
-                        .style(square_style.as_custom())
-                };
+    // The analysis engine is restarted when its engine or options change.
+    // MultiPV is set with the other options when the engine starts, and
+    // changed on the fly afterwards.
+    fn assign_analysis_engine(&mut self) {
+        if self.analysing {
+            return;
+        }
+
+        self.analysis_engine.engine_path = self.engine_manager.engine_path(EngineSlot::Analysis);
+        self.analysis_engine.options = self.engine_manager.option_values(EngineSlot::Analysis);
+        if let Some(definition) = self.engine_manager.engine_in(EngineSlot::Analysis) {
+            self.analysis_engine.set_timeouts(definition);
+        }
+        self.analysis_engine
+            .options
+            .push(("MultiPV".to_string(), self.settings.multipv.to_string()));
+    }
+
+    // Let the analysis engine search the position on the board, given as
+    // its FEN and the position with the moves of the game that led to it, so
+    // the engine knows about repetitions.
+    fn analyse_position(&self) {
+        self.send_to_analysis(format!(
+            "{}\n{}",
+            self.board.create_fen(),
+            self.game.uci_position(self.game.current)
+        ));
+    }
+
+    fn send_to_analysis(&self, message: String) {
+        if let Some(sender) = &self.analysis_sender {
+            if let Err(e) = sender.blocking_send(message) {
+                eprintln!("Lost connection with the analysis engine: {}", e);
+            }
+        }
+    }
+
+    // What a playing engine is asked to search: the FEN of the position on
+    // the board, which its info lines are tagged with, followed by the
+    // position with the moves of the game and the go command.
+    fn search_request(&mut self) -> String {
+        let settings = &self.settings;
+        let go = match settings.search_limit {
+            SearchLimit::Clock => {
+                // The clock only ticks every so often, so it is brought up to
+                // date first.
+                self.clock.tick();
+                self.clock
+                    .go_command(self.board.side_to_move(), settings.safety_margin_ms)
             }
+            SearchLimit::MoveTime => format!(
+                "go movetime {}",
+                settings
+                    .search_move_time_ms
+                    .saturating_sub(settings.safety_margin_ms)
+                    .max(1)
+            ),
+            SearchLimit::Nodes => format!("go nodes {}", settings.search_nodes),
+            SearchLimit::Depth => format!("go depth {}", settings.search_depth),
+        };
+
+        format!(
+            "{}\n{}\n{}",
+            self.board.create_fen(),
+            self.game.uci_position(self.game.current),
+            go
+        )
+    }
 
-            board_col = board_col.push(button);
+    // Send a message to the playing engine in the slot. An engine that is
+    // gone fails like one that crashed, so the failure is logged and a
+    // restart offered.
+    fn send_to_engine(&self, slot: EngineSlot, message: String) -> Command<Message> {
+        let sender = match slot {
+            EngineSlot::White => &self.engine1_sender,
+            EngineSlot::Black => &self.engine2_sender,
+            EngineSlot::Analysis => return Command::none(),
+        };
+        let Some(sender) = sender else {
+            return Command::none();
+        };
+
+        match sender.blocking_send(message) {
+            Ok(()) => Command::none(),
+            Err(e) => {
+                let error = EngineError::Io(format!("lost connection: {}", e));
+                Command::perform(async move { Message::EngineFailed(slot, error) }, |msg| msg)
+            }
         }
+    }
 
-        if show_coordinates {
-            board_col = board_col.push(
-                Container::new(Text::new((row[rank as usize - 1]).to_string()).size(15))
-                    .align_y(iced::alignment::Vertical::Top)
-                    .align_x(iced::alignment::Horizontal::Left)
-                    .padding(5)
-                    .width(board_height),
-            );
+    // Playing engines are told when a new game starts, so they don't keep
+    // what they learned in the last one.
+    fn new_game_for_engines(&self) {
+        for sender in [&self.engine1_sender, &self.engine2_sender]
+            .into_iter()
+            .flatten()
+        {
+            if let Err(e) = sender.blocking_send("ucinewgame".to_string()) {
+                eprintln!("Lost connection with the engine: {}", e);
+            }
+        }
+    }
+
+    // The colour the engine in the slot plays.
+    fn engine_side(&self, slot: EngineSlot) -> Side {
+        match (self.settings.game_mode, slot) {
+            (GameMode::PlayerEngine, _) => self.settings.player_side as usize ^ 1,
+            (_, EngineSlot::Black) => Sides::BLACK,
+            _ => Sides::WHITE,
         }
+    }
+
+    // The slot of the engine that has to make the next move, if any.
+    fn engine_to_move(&self) -> Option<EngineSlot> {
+        let side = self.board.side_to_move();
+
+        match self.settings.game_mode {
+            GameMode::EngineEngine if side == Sides::WHITE => Some(EngineSlot::White),
+            GameMode::EngineEngine => Some(EngineSlot::Black),
+            GameMode::PlayerEngine if self.settings.player_side as usize != side => {
+                Some(EngineSlot::White)
+            }
+            _ => None,
+        }
+    }
 
-        board_row = board_row.push(board_col);
-        board_col = Column::new().spacing(0).align_items(Alignment::Center);
-    }
-
-    if show_coordinates {
-        if !flip_board {
-            board_row = board_row.push(
-                column![
-                    Text::new("8").size(15).height(board_height),
-                    Text::new("7").size(15).height(board_height),
-                    Text::new("6").size(15).height(board_height),
-                    Text::new("5").size(15).height(board_height),
-                    Text::new("4").size(15).height(board_height),
-                    Text::new("3").size(15).height(board_height),
-                    Text::new("2").size(15).height(board_height),
-                    Text::new("1").size(15).height(board_height),
-                ]
-                .padding(5),
+    // Start a failed engine again. A playing engine gets a new subscription,
+    // which starts a new process.
+    fn restart_engine(&mut self, slot: EngineSlot) {
+        self.engine_error = None;
+
+        match slot {
+            EngineSlot::White => {
+                self.engine1.restarts += 1;
+                self.engine1_sender = None;
+            }
+            EngineSlot::Black => {
+                self.engine2.restarts += 1;
+                self.engine2_sender = None;
+            }
+            EngineSlot::Analysis => {
+                self.analysis_engine.position = self.board.create_fen();
+                self.analysing = true;
+            }
+        }
+    }
+
+    // A clock for a new game, that starts with the first move.
+    fn new_clock(&mut self) {
+        self.clock = Clock::with_time_control(&self.settings.time_control);
+        self.game_over = false;
+    }
+
+    // The side that ran out of time loses, unless the other side can't mate
+    // anymore.
+    fn flag_fall(&mut self, side: Side) -> Command<Message> {
+        let winner = side ^ 1;
+        let fen = self.board.create_fen();
+
+        if !can_mate(&self.board, winner) {
+            return self.end_game(
+                "1/2-1/2",
+                "time forfeit",
+                format!("Flag fell but no mating material, draw\nFen: {}", fen),
             );
+        }
+
+        let (result, name) = if winner == Sides::WHITE {
+            ("1-0", "white")
         } else {
-            board_row = board_row.push(column![
-                Text::new("1").size(15).height(board_height),
-                Text::new("2").size(15).height(board_height),
-                Text::new("3").size(15).height(board_height),
-                Text::new("4").size(15).height(board_height),
-                Text::new("5").size(15).height(board_height),
-                Text::new("6").size(15).height(board_height),
-                Text::new("7").size(15).height(board_height),
-                Text::new("8").size(15).height(board_height),
-            ]);
+            ("0-1", "black")
+        };
+        self.end_game(
+            result,
+            "time forfeit",
+            format!("Flag fell, {} won on time\nFen: {}", name, fen),
+        )
+    }
+
+    // End the game when the side to move has no legal moves, as mate or
+    // stalemate. The board decides this, not what an engine claims.
+    fn mate_or_stalemate(&mut self) -> Option<Command<Message>> {
+        if self.game_over || !self.board.legal_moves(&self.movegen).is_empty() {
+            return None;
+        }
+        let fen = self.board.create_fen();
+
+        if self.king_in_check().is_none() {
+            return Some(self.end_game("1/2-1/2", "normal", format!("Stalemate\nFen: {}", fen)));
+        }
+        let (result, winner) = if self.board.side_to_move() == Sides::WHITE {
+            ("0-1", "black")
+        } else {
+            ("1-0", "white")
+        };
+        Some(self.end_game(
+            result,
+            "normal",
+            format!("Check mate, {} won the game\nFen: {}", winner, fen),
+        ))
+    }
+
+    // End a game that is mated or stalemated, or a game between engines the
+    // draw rules or the adjudication rules end, instead of asking for a move.
+    fn judge_engine_game(&mut self) -> Option<Command<Message>> {
+        if let Some(command) = self.mate_or_stalemate() {
+            return Some(command);
+        }
+        if self.settings.game_mode != GameMode::EngineEngine {
+            return None;
+        }
+        let fen = self.board.create_fen();
+
+        if let Some(rule) = draw_by_rule(&self.board) {
+            return Some(self.end_game(
+                "1/2-1/2",
+                "normal",
+                format!("Draw by {}\nFen: {}", rule, fen),
+            ));
+        }
+
+        let verdict = self
+            .tournament_tab
+            .config
+            .adjudication
+            .adjudicate(&self.game)?;
+        Some(self.end_game(
+            verdict.result.pgn(),
+            verdict.termination,
+            format!("{}\nFen: {}", verdict.description, fen),
+        ))
+    }
+
+    // Stop the clock and record the result. In a tournament the result is
+    // logged and the next game starts.
+    fn end_game(
+        &mut self,
+        result: &str,
+        termination: &str,
+        description: String,
+    ) -> Command<Message> {
+        self.game_over = true;
+        self.clock.stop();
+        self.game.set_header("Result", result);
+        self.game.set_header("Termination", termination);
+
+        let Some(tournament) = &mut self.tournament_tab.running else {
+            println!("{}", description);
+            return Command::none();
+        };
+        let Some(index) = self.tournament_tab.current_game.take() else {
+            return Command::none();
+        };
+
+        if let Some(result) = GameResult::from_pgn(result) {
+            tournament.record(index, result);
+        }
+        let game = &tournament.games[index];
+        let mut line = format!(
+            "Game {}: {} - {} {} ({})\n{}",
+            index + 1,
+            tournament.engines[game.white],
+            tournament.engines[game.black],
+            result,
+            termination,
+            description
+        );
+        if let Some(sprt) = tournament.sprt_summary() {
+            line.push_str(&format!("\n{}", sprt));
+        }
+        if let Err(e) = persistence::log_tournament(&line, false) {
+            eprintln!("Could not write the tournament log: {}", e);
+        }
+        if let Err(e) = persistence::save_tournament(tournament) {
+            eprintln!("Could not save the tournament: {}", e);
+        }
+
+        Command::perform(async { Message::NextGame }, |msg| msg)
+    }
+
+    // Let the engines play the games of the tournament, from the first game
+    // that hasn't been played yet.
+    fn start_tournament(&mut self, tournament: Tournament) -> Command<Message> {
+        if let Some(name) = tournament
+            .engines
+            .iter()
+            .find(|name| self.engine_manager.engine_named(name).is_none())
+        {
+            self.tournament_tab.status = not_registered(name);
+            return Command::none();
+        }
+        if let Err(e) = persistence::save_tournament(&tournament) {
+            eprintln!("Could not save the tournament: {}", e);
+        }
+        self.tournament_tab.running = Some(tournament);
+        self.tournament_tab.resumable = false;
+        self.settings.game_mode = GameMode::EngineEngine;
+        self.engine1_status = EngineStatus::TurnedOn;
+        self.engine2_status = EngineStatus::TurnedOn;
+
+        Command::perform(async { Message::NextGame }, |msg| msg)
+    }
+
+    // Stop the tournament. The game on the board is played again when the
+    // tournament is resumed.
+    fn stop_tournament(&mut self) {
+        if let Some(tournament) = self.tournament_tab.running.take() {
+            self.tournament_tab.resumable = !tournament.is_over();
         }
+        self.tournament_tab.current_game = None;
+        self.stop_engines();
     }
 
+    // Put the engines of the game in the playing slots and name them in the
+    // PGN headers. An engine that takes over a slot is started anew. The game
+    // isn't started when one of its engines is no longer registered, as its
+    // result would go to that engine's name.
+    fn start_tournament_game(&mut self, index: usize) -> Result<(), String> {
+        let Some(tournament) = &self.tournament_tab.running else {
+            return Ok(());
+        };
+        let game = tournament.games[index].clone();
+        let white = tournament.engines[game.white].clone();
+        let black = tournament.engines[game.black].clone();
+        let opening = tournament.opening(index).cloned();
+        let games = tournament.games.len();
+
+        let mut definitions = vec![];
+        for name in [&white, &black] {
+            match self.engine_manager.engine_named(name) {
+                Some(definition) => definitions.push(definition.clone()),
+                None => return Err(not_registered(name)),
+            }
+        }
+
+        for (slot, definition) in [EngineSlot::White, EngineSlot::Black]
+            .into_iter()
+            .zip(&definitions)
+        {
+            let (engine, sender) = match slot {
+                EngineSlot::White => (&mut self.engine1, &mut self.engine1_sender),
+                _ => (&mut self.engine2, &mut self.engine2_sender),
+            };
+            if engine.engine_path != definition.path || engine.options != definition.option_values {
+                engine.engine_path = definition.path.clone();
+                engine.options = definition.option_values.clone();
+                engine.restarts += 1;
+                *sender = None;
+            }
+            engine.set_timeouts(definition);
+            engine.search_up_to = self.settings.search_depth;
+        }
+
+        if let Some(opening) = opening {
+            self.play_opening(&opening);
+            self.game.set_header("Opening", &opening.name);
+        }
+        self.game.set_header("Event", "Engine tournament");
+        self.game.set_header("Round", &(game.round + 1).to_string());
+        self.game.set_header("White", &white);
+        self.game.set_header("Black", &black);
+        self.tournament_tab.current_game = Some(index);
+        self.tournament_tab.status = format!(
+            "Round {}, game {} of {}: {} - {}",
+            game.round + 1,
+            index + 1,
+            games,
+            white,
+            black
+        );
+
+        Ok(())
+    }
+
+    // Set up the position of the opening, with its moves in the game. The
+    // clock only starts after them.
+    fn play_opening(&mut self, opening: &Opening) {
+        (self.game, self.board) = openings::start_game(opening, &self.movegen);
+    }
+
+    // All games have been played. The final standings stay in the
+    // tournament tab until the tournament is closed.
+    fn finish_tournament(&mut self) {
+        self.stop_engines();
+        self.tournament_tab.current_game = None;
+        let Some(tournament) = &self.tournament_tab.running else {
+            return;
+        };
+
+        self.tournament_tab.status = match tournament.sprt_result() {
+            Some(result) => format!(
+                "Stopped after {} games, SPRT {}",
+                tournament.games_played(),
+                result
+            ),
+            None => format!("Tournament over after {} games", tournament.games_played()),
+        };
+        let mut summary = format!(
+            "{}\n{}",
+            self.tournament_tab.status,
+            tournament.crosstable()
+        );
+        for (title, stats) in tournament.test_results() {
+            summary.push_str(&format!("\n\n{}\n{}", title, stats.summary()));
+        }
+        if let Some(sprt) = tournament.sprt_summary() {
+            summary.push_str(&format!("\n{}", sprt));
+        }
+        println!("{}", summary);
+        if let Err(e) = persistence::log_tournament(&summary, false) {
+            eprintln!("Could not write the tournament log: {}", e);
+        }
+    }
+
+    // Quit the playing engines and end the game on the board. The slots get
+    // the engines picked in the engines tab again.
+    fn stop_engines(&mut self) {
+        for sender in [self.engine1_sender.take(), self.engine2_sender.take()]
+            .into_iter()
+            .flatten()
+        {
+            let _ = sender.blocking_send("quit".to_string());
+        }
+        self.engine1_status = EngineStatus::TurnedOff;
+        self.engine2_status = EngineStatus::TurnedOff;
+        self.game_over = true;
+        self.clock.stop();
+        self.assign_engines();
+    }
+
+    // The settings of the settings tab and the engine manager together.
+    fn config(&self) -> UIConfig {
+        UIConfig {
+            engines: self.engine_manager.engines.clone(),
+            engine_slots: self.engine_manager.slots.clone(),
+            tournament: self.tournament_tab.config.clone(),
+            ..self.settings.config()
+        }
+    }
+
+    fn save_config(&self) {
+        if let Err(e) = persistence::save(&self.config()) {
+            eprintln!("Could not save the settings: {}", e);
+        }
+    }
+
+    // Make a move on the board and record it in the game tree. Returns false
+    // if the move was illegal and has not been made.
+    fn play_move(&mut self, m: Move) -> bool {
+        let san = move_to_san(&self.board, &self.movegen, m);
+        if !self.board.make_move(m, &self.movegen) {
+            return false;
+        }
+
+        self.game.play(m, san);
+        if !self.game_over {
+            self.clock.press(self.board.side_to_move() ^ 1);
+        }
+        self.analyse_position();
+        true
+    }
+
+    // Show the position of the current node of the game tree, after
+    // navigating through the moves.
+    fn show_current_node(&mut self) {
+        self.board = self.game.board_at(self.game.current, &self.movegen);
+        self.from_square = None;
+        self.highlighted_squares.clear();
+        self.promotion.show_promotion_prompt = false;
+        self.promotion.promotion_square = None;
+        self.analyse_position();
+    }
+
+    // Squares the piece on the given square can legally move to.
+    fn legal_targets(&self, square: Square) -> Vec<Square> {
+        let mut targets = vec![];
+        for m in self.board.legal_moves(&self.movegen) {
+            if m.from() == square && !targets.contains(&m.to()) {
+                targets.push(m.to());
+            }
+        }
+
+        targets
+    }
+
+    // The last move played on the board, as a from and to square.
+    fn last_move(&self) -> Option<(Square, Square)> {
+        let count = self.board.history.len();
+        if count == 0 {
+            return None;
+        }
+
+        let m = self.board.history.get_ref(count - 1).next_move;
+        Some((m.from(), m.to()))
+    }
+
+    // The square of the king of the side to move, if that king is in check.
+    fn king_in_check(&self) -> Option<Square> {
+        let side = self.board.side_to_move();
+        let king = self.board.king_square(side);
+
+        self.movegen
+            .square_attacked(&self.board, side ^ 1, king)
+            .then_some(king)
+    }
+
+    // The board is shown from black's perspective when the user asks for it,
+    // or automatically when the human plays black against the engine.
+    fn board_flipped(&self) -> bool {
+        let playing_black = self.settings.game_mode == GameMode::PlayerEngine
+            && self.settings.player_side as usize == Sides::BLACK;
+
+        self.settings.flip_board != playing_black
+    }
+
+    // The side whose pieces the user is allowed to pick up, if any.
+    fn movable_side(&self) -> Option<Side> {
+        let side = self.board.side_to_move();
+
+        match self.settings.game_mode {
+            GameMode::PlayerPlayer => Some(side),
+            GameMode::PlayerEngine if self.settings.player_side as usize == side => Some(side),
+            _ => None,
+        }
+    }
+}
+
+// Why a tournament can't go on with an engine that was removed.
+fn not_registered(name: &str) -> String {
+    format!(
+        "Engine {} is not registered anymore, add it again to play its games",
+        name
+    )
+}
+
+fn eval_bar_white(_theme: &Theme) -> container::Appearance {
+    container::Appearance {
+        background: Some(Background::Color(Color::WHITE)),
+        ..Default::default()
+    }
+}
+
+fn eval_bar_black(_theme: &Theme) -> container::Appearance {
+    container::Appearance {
+        background: Some(Background::Color(Color::BLACK)),
+        ..Default::default()
+    }
+}
+
+fn main_view<'a>(
+    board: &'a Board,
+    flip_board: bool,
+    show_coordinates: bool,
+    _search: u32,
+    settings_tab: Element<'a, Message, iced::Renderer<Theme>>,
+    engine_started: bool,
+    analysing: bool,
+    size: Size,
+    highlighted_squares: &'a [Square],
+    highlights: Highlights,
+    colors: BoardColors,
+    pieces: &'a PieceSet,
+    promotion: &'a Promotions,
+    movable_side: Option<Side>,
+    player_side: Side,
+    clock: &Clock,
+    eval: f32,
+    eval_label: &'a str,
+    game: &'a GameTree,
+    analysis: &'a Analysis,
+    standings: Option<Element<'a, Message, iced::Renderer<Theme>>>,
+) -> Element<'a, Message, iced::Renderer<Theme>> {
+    // Leave room for the evaluation bar, the clocks or settings and the
+    // controls below the board.
+    let board_size = (size.width - SIDE_PANEL_WIDTH - EVAL_BAR_WIDTH)
+        .min(size.height - CONTROLS_HEIGHT)
+        .max(MIN_BOARD_SIZE);
+
+    // The clock of the side at the top of the board is shown at the top.
+    let white_time = format_clock(clock.wtime);
+    let black_time = format_clock(clock.btime);
+    let black_clock = Button::new(Text::new(format!("Black: {}", black_time)));
+    let white_clock = Button::new(Text::new(format!("White: {}", white_time)));
+    let (top_clock, bottom_clock) = if flip_board {
+        (white_clock, black_clock)
+    } else {
+        (black_clock, white_clock)
+    };
+
+    let timer_row = column![top_clock, bottom_clock]
+        .padding(30)
+        .spacing(board_size - 100.0);
+
+    let chess_board = ChessBoard::new(board, highlighted_squares, promotion)
+        .highlights(highlights)
+        .annotations(&game.current_node().annotations)
+        .colors(colors)
+        .pieces(pieces)
+        .movable_side(movable_side)
+        .show_coordinates(show_coordinates)
+        .flipped(flip_board)
+        .width(board_size)
+        .height(board_size);
+
     let mut side_to_play = row![];
 
     if board.side_to_move() == Sides::WHITE {
@@ -837,20 +1572,22 @@ fn main_view<'a>(
         side_to_play = side_to_play.push(Text::new("Black to move"));
     }
 
+    // Without engines the clocks aren't shown next to the board.
+    if !engine_started {
+        side_to_play = side_to_play
+            .push(Text::new(format!("White {}", white_time)))
+            .push(Text::new(format!("Black {}", black_time)))
+            .spacing(20);
+    }
+
     let game_mode_row = row![
         Text::new("Play as"),
-        Radio::new(
-            "White",
-            Sides::WHITE,
-            Some(board.side_to_move()),
-            Message::SelectSideToMove
-        ),
-        Radio::new(
-            "Black",
-            Sides::BLACK,
-            Some(board.side_to_move()),
-            Message::SelectSideToMove
-        )
+        Radio::new("White", Sides::WHITE, Some(player_side), |side| {
+            Message::Settings(SettingsMessage::SelectPlayerSide(side))
+        }),
+        Radio::new("Black", Sides::BLACK, Some(player_side), |side| {
+            Message::Settings(SettingsMessage::SelectPlayerSide(side))
+        })
     ]
     .spacing(10)
     .padding(10)
@@ -865,41 +1602,136 @@ fn main_view<'a>(
     navigation_row =
         navigation_row.push(Button::new(Text::new("Kiwipete")).on_press(Message::ChangeStartPos));
 
+    navigation_row = navigation_row.push(
+        Button::new(Text::new("Tournament"))
+            .on_press(Message::SelectSidePanel(SidePanel::Tournament)),
+    );
+
+    let analysis_label = if analysing {
+        "Stop analysis"
+    } else {
+        "Analyse"
+    };
     navigation_row = navigation_row
-        .push(Button::new(Text::new("Tournament")).on_press(Message::StartTournament));
+        .push(Button::new(Text::new(analysis_label)).on_press(Message::ToggleAnalysis));
+
+    let move_row = row![
+        Button::new(Text::new("<<")).on_press(Message::Navigate(Navigation::Start)),
+        Button::new(Text::new("<")).on_press(Message::Navigate(Navigation::Back)),
+        Button::new(Text::new(">")).on_press(Message::Navigate(Navigation::Forward)),
+        Button::new(Text::new(">>")).on_press(Message::Navigate(Navigation::End)),
+        Button::new(Text::new("Clear arrows")).on_press(Message::ClearAnnotations),
+        Button::new(Text::new("Copy PGN")).on_press(Message::CopyPgn),
+        Button::new(Text::new("Paste PGN")).on_press(Message::PastePgn),
+    ]
+    .padding(3)
+    .spacing(5);
+
+    // Vertical evaluation bar, white's share grows from white's side of the
+    // board. The portions are in tenths of a percent so the animation is
+    // smooth.
+    let white_share = (eval.clamp(0.0, 100.0) * 10.0) as u16;
+    let black_part = Container::new(Space::with_height(Length::Fill))
+        .height(Length::FillPortion(1000 - white_share))
+        .width(Length::Fill)
+        .style(eval_bar_black as fn(&Theme) -> container::Appearance);
+    let white_part = Container::new(Space::with_height(Length::Fill))
+        .height(Length::FillPortion(white_share))
+        .width(Length::Fill)
+        .style(eval_bar_white as fn(&Theme) -> container::Appearance);
+    let bar = if flip_board {
+        column![white_part, black_part]
+    } else {
+        column![black_part, white_part]
+    }
+    .width(10)
+    .height(board_size - 20.0)
+    .padding(0);
 
-    let evaluation_bar = column![progress_bar(0.0..=100.0, eval)
-        .vertical(true)
-        .height(100 * 8)
-        .width(10)
-        .style(ProgressBar::Danger)]
-    .padding(5);
+    let evaluation_bar = column![Text::new(eval_label).size(10), bar]
+        .spacing(5)
+        .width(EVAL_BAR_WIDTH)
+        .align_items(Alignment::Center);
 
     row![
-        evaluation_bar,
+        Container::new(evaluation_bar).padding(5),
         column![
-            board_row,
-            column![side_to_play, game_mode_row, navigation_row]
-                .width(board_height * 8)
+            chess_board,
+            column![side_to_play, game_mode_row, navigation_row, move_row]
+                .width(board_size)
                 .height(Length::Fill)
                 .align_items(Alignment::Center)
         ]
         .padding(5),
         if !engine_started {
-            column![settings_tab]
+            column![
+                settings_tab,
+                analysis.view(),
+                move_list(game),
+                eval_graph(game)
+            ]
         } else {
-            column![timer_row]
+            // A running tournament shows its standings under the clocks.
+            let mut panel = column![timer_row];
+            if let Some(standings) = standings {
+                panel = panel.push(standings);
+            }
+            panel
+                .push(analysis.view())
+                .push(move_list(game))
+                .push(eval_graph(game))
         }
     ]
     .into()
 }
 
+// The moves of the line through the current position, two per row. Clicking a
+// move shows the position after it.
+fn move_list<'a>(game: &GameTree) -> Element<'a, Message, iced::Renderer<Theme>> {
+    let mut moves = Column::new().spacing(2).padding(10);
+    let mut current_row = Row::new().spacing(5).align_items(Alignment::Center);
+    let first_ply = game.first_ply();
+
+    // A game starting with black to move leaves white's first move empty.
+    if !first_ply.is_multiple_of(2) {
+        current_row = current_row
+            .push(Text::new(format!("{}.", first_ply / 2 + 1)).width(40))
+            .push(Text::new("...").width(80));
+    }
+
+    for (ply, node) in (first_ply..).zip(game.line()) {
+        if ply.is_multiple_of(2) {
+            current_row = current_row.push(Text::new(format!("{}.", ply / 2 + 1)).width(40));
+        }
+
+        let style = if node == game.current {
+            iced::theme::Button::Primary
+        } else {
+            iced::theme::Button::Text
+        };
+        current_row = current_row.push(
+            Button::new(Text::new(game.nodes[node].san.clone()))
+                .width(80)
+                .style(style)
+                .on_press(Message::GoToMove(node)),
+        );
+
+        if !ply.is_multiple_of(2) {
+            moves = moves.push(current_row);
+            current_row = Row::new().spacing(5).align_items(Alignment::Center);
+        }
+    }
+    moves = moves.push(current_row);
+
+    scrollable(moves).height(Length::Fill).into()
+}
+
 pub trait Tab {
     type Message;
 
     fn title(&self) -> String;
 
-    // fn tab_label(&self) -> TabLabel;
+    fn tab_label(&self) -> TabLabel;
 
     fn view(&self) -> Element<Message, iced::Renderer<Theme>> {
         let column = Column::new()
@@ -919,40 +1751,26 @@ pub trait Tab {
     fn content(&self) -> Element<Message, iced::Renderer<Theme>>;
 }
 
-pub fn show_promotion_options() -> Element<'static, Message, iced::Renderer<Theme>> {
+pub fn show_promotion_options(
+    pieces: &PieceSet,
+) -> Element<'static, Message, iced::Renderer<Theme>> {
     Column::new()
         .push(Text::new("Select promotion piece:"))
         .push(
-            Button::new(Svg::from_path(format!(
-                "{}/pieces{}",
-                env!("CARGO_MANIFEST_DIR"),
-                "/wQ.svg"
-            )))
-            .on_press(Message::PromotionSelected(PromotionChoice::Queen)),
+            Button::new(Svg::new(pieces.svg(Sides::WHITE, Pieces::QUEEN)))
+                .on_press(Message::PromotionSelected(PromotionChoice::Queen)),
         )
         .push(
-            Button::new(Svg::from_path(format!(
-                "{}/pieces{}",
-                env!("CARGO_MANIFEST_DIR"),
-                "/wR.svg"
-            )))
-            .on_press(Message::PromotionSelected(PromotionChoice::Rook)),
+            Button::new(Svg::new(pieces.svg(Sides::WHITE, Pieces::ROOK)))
+                .on_press(Message::PromotionSelected(PromotionChoice::Rook)),
         )
         .push(
-            Button::new(Svg::from_path(format!(
-                "{}/pieces{}",
-                env!("CARGO_MANIFEST_DIR"),
-                "/wB.svg"
-            )))
-            .on_press(Message::PromotionSelected(PromotionChoice::Bishop)),
+            Button::new(Svg::new(pieces.svg(Sides::WHITE, Pieces::BISHOP)))
+                .on_press(Message::PromotionSelected(PromotionChoice::Bishop)),
         )
         .push(
-            Button::new(Svg::from_path(format!(
-                "{}/pieces{}",
-                env!("CARGO_MANIFEST_DIR"),
-                "/wN.svg"
-            )))
-            .on_press(Message::PromotionSelected(PromotionChoice::Knight)),
+            Button::new(Svg::new(pieces.svg(Sides::WHITE, Pieces::KNIGHT)))
+                .on_press(Message::PromotionSelected(PromotionChoice::Knight)),
         )
         .into()
 }